@@ -0,0 +1,258 @@
+//! Parsing benchmarks over KiCad files. Besides a footprint file as written by
+//! KiCad, there are generated files: The footprint looks like one of the QFP
+//! packages of the official library, and the board contains many of them together
+//! with the tracks between their pads.
+
+// the fields are only written by the deserializer
+#![allow(dead_code)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use serde::Deserialize;
+use serde_kicad_sexpr::{de::Deserializer, Value};
+use std::fmt::Write as _;
+
+#[derive(Deserialize)]
+#[serde(rename = "layer")]
+struct Layer(String);
+
+#[derive(Deserialize)]
+#[serde(rename = "width")]
+struct Width(f32);
+
+#[derive(Deserialize)]
+#[serde(rename = "net")]
+struct Net(u32, String);
+
+#[derive(Deserialize)]
+#[serde(rename = "at")]
+struct Position {
+	x: f32,
+	y: f32,
+	rotation: Option<f32>
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "fp_line")]
+struct Line {
+	start: (f32, f32),
+	end: (f32, f32),
+	layer: Layer,
+	width: Width
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PadType {
+	Smd,
+	ThruHole
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PadShape {
+	Rect,
+	Roundrect
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "pad")]
+struct Pad {
+	number: String,
+	ty: PadType,
+	shape: PadShape,
+	at: Position,
+	size: (f32, f32),
+	layers: Vec<String>,
+	net: Option<Net>
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "footprint")]
+struct Footprint {
+	name: String,
+	layer: Layer,
+	#[serde(default, rename = "fp_line*")]
+	lines: Vec<Line>,
+	#[serde(default, rename = "pad*")]
+	pads: Vec<Pad>
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "segment")]
+struct Segment {
+	start: (f32, f32),
+	end: (f32, f32),
+	width: Width,
+	layer: Layer,
+	net: (u32,)
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "kicad_pcb")]
+struct Board {
+	#[serde(default, rename = "footprint*")]
+	footprints: Vec<Footprint>,
+	#[serde(default, rename = "segment*")]
+	segments: Vec<Segment>
+}
+
+/// Write a QFP footprint with `pins` pins. Placed footprints have a position and
+/// nets on their pads.
+fn write_footprint(out: &mut String, index: usize, pins: usize, placed: bool) {
+	let side = pins / 4;
+	let half = side as f32 * 0.25;
+	writeln!(out, "  (footprint \"Package_QFP:LQFP-{pins}\"").unwrap();
+	writeln!(out, "    (layer \"F.Cu\") (tedit 5D9F72AF)").unwrap();
+	writeln!(out, "    (tstamp 0c2a44f1-{index:04x}-4b4e-9d0e)").unwrap();
+	if placed {
+		let (x, y) = (index % 10 * 20, index / 10 * 20);
+		writeln!(out, "    (at {x} {y})").unwrap();
+	}
+	writeln!(out, "    (descr \"LQFP, {pins} Pin, 0.5mm pitch\")").unwrap();
+	writeln!(out, "    (tags \"LQFP QFP\")").unwrap();
+	writeln!(out, "    (attr smd)").unwrap();
+	writeln!(
+		out,
+		"    (fp_text reference \"U{index}\" (at 0 -{:.2}) (layer \"F.SilkS\")",
+		half + 2.0
+	)
+	.unwrap();
+	writeln!(out, "      (effects (font (size 1 1) (thickness 0.15)))").unwrap();
+	writeln!(out, "      (tstamp 7b5e1c42-1f3a-4f0e-a1b2-{index:012x})").unwrap();
+	writeln!(out, "    )").unwrap();
+	for (x1, y1, x2, y2) in [
+		(-half, -half, half, -half),
+		(half, -half, half, half),
+		(half, half, -half, half),
+		(-half, half, -half, -half)
+	] {
+		writeln!(
+			out,
+			"    (fp_line (start {x1:.2} {y1:.2}) (end {x2:.2} {y2:.2}) \
+			 (layer \"F.SilkS\") (width 0.12) (tstamp 3f0d2a5c-{index:04x}))"
+		)
+		.unwrap();
+	}
+	for pin in 0..pins {
+		let offset = (pin % side) as f32 * 0.5 - half + 0.25;
+		let (x, y, rotation) = match pin / side {
+			0 => (-half - 0.9, offset, ""),
+			1 => (offset, half + 0.9, " 90"),
+			2 => (half + 0.9, -offset, ""),
+			_ => (-offset, -half - 0.9, " 90")
+		};
+		write!(
+			out,
+			"    (pad \"{}\" smd roundrect (at {x:.4} {y:.4}{rotation}) \
+			 (size 1.475 0.3) (layers \"F.Cu\" \"F.Paste\" \"F.Mask\") \
+			 (roundrect_rratio 0.25)",
+			pin + 1
+		)
+		.unwrap();
+		if placed {
+			write!(out, " (net {} \"Net-(U{index}-Pad{})\")", pin + 1, pin + 1)
+				.unwrap();
+		}
+		writeln!(out, " (tstamp 9a8b7c6d-{index:04x}-{pin:04x}))").unwrap();
+	}
+	writeln!(out, "    (model \"${{KICAD6_3DMODEL_DIR}}/Package_QFP.3dshapes/LQFP.wrl\"")
+		.unwrap();
+	writeln!(out, "      (offset (xyz 0 0 0)) (scale (xyz 1 1 1)) (rotate (xyz 0 0 0)))")
+		.unwrap();
+	writeln!(out, "  )").unwrap();
+}
+
+fn footprint(pins: usize) -> String {
+	let mut out = String::new();
+	write_footprint(&mut out, 0, pins, false);
+	out.trim_start().to_owned()
+}
+
+fn board(footprints: usize, segments: usize) -> String {
+	let mut out = String::new();
+	writeln!(out, "(kicad_pcb (version 20211014) (generator pcbnew)").unwrap();
+	writeln!(out, "  (general (thickness 1.6))").unwrap();
+	writeln!(out, "  (paper \"A4\")").unwrap();
+	writeln!(out, "  (setup (pad_to_mask_clearance 0) (solder_mask_min_width 0))")
+		.unwrap();
+	for net in 0..64 {
+		writeln!(out, "  (net {net} \"Net-{net}\")").unwrap();
+	}
+	for index in 0..footprints {
+		write_footprint(&mut out, index, 64, true);
+	}
+	for index in 0..segments {
+		let (x, y) = ((index % 100) as f32 * 1.27, (index / 100) as f32 * 0.635);
+		writeln!(
+			out,
+			"  (segment (start {x:.3} {y:.3}) (end {:.3} {y:.3}) (width 0.25) \
+			 (layer \"F.Cu\") (net {}) (tstamp 5c1e0a3b-{index:08x}))",
+			x + 1.27,
+			index % 64
+		)
+		.unwrap();
+	}
+	out.push_str(")\n");
+	out
+}
+
+fn parse_typed<'de, T>(input: &'de str) -> T
+where
+	T: Deserialize<'de>
+{
+	let mut de = Deserializer::from_str(input).lenient(true);
+	T::deserialize(&mut de).unwrap()
+}
+
+fn parse_value(input: &str) -> Value {
+	serde_kicad_sexpr::from_str(input).unwrap()
+}
+
+/// A footprint of the official library, as written by KiCad 6.
+const SOIC_8: &str = include_str!("data/SOIC-8_3.9x4.9mm_P1.27mm.kicad_mod");
+
+fn bench_library_footprint(c: &mut Criterion) {
+	let parsed = parse_typed::<Footprint>(SOIC_8);
+	assert_eq!(parsed.lines.len(), 13);
+	assert_eq!(parsed.pads.len(), 8);
+
+	let mut group = c.benchmark_group("library_footprint");
+	group.throughput(Throughput::Bytes(SOIC_8.len() as u64));
+	group.bench_function("typed", |b| {
+		b.iter(|| parse_typed::<Footprint>(black_box(SOIC_8)))
+	});
+	group.bench_function("value", |b| b.iter(|| parse_value(black_box(SOIC_8))));
+	group.finish();
+}
+
+fn bench_footprint(c: &mut Criterion) {
+	let input = footprint(64);
+	assert_eq!(parse_typed::<Footprint>(&input).pads.len(), 64);
+
+	let mut group = c.benchmark_group("footprint");
+	group.throughput(Throughput::Bytes(input.len() as u64));
+	group.bench_function("typed", |b| {
+		b.iter(|| parse_typed::<Footprint>(black_box(&input)))
+	});
+	group.bench_function("value", |b| b.iter(|| parse_value(black_box(&input))));
+	group.finish();
+}
+
+fn bench_board(c: &mut Criterion) {
+	let input = board(100, 5000);
+	let parsed = parse_typed::<Board>(&input);
+	assert_eq!(parsed.footprints.len(), 100);
+	assert_eq!(parsed.segments.len(), 5000);
+
+	let mut group = c.benchmark_group("board");
+	group.throughput(Throughput::Bytes(input.len() as u64));
+	group.sample_size(20);
+	group.bench_function("typed", |b| {
+		b.iter(|| parse_typed::<Board>(black_box(&input)))
+	});
+	group.bench_function("value", |b| b.iter(|| parse_value(black_box(&input))));
+	group.finish();
+}
+
+criterion_group!(benches, bench_library_footprint, bench_footprint, bench_board);
+criterion_main!(benches);
@@ -0,0 +1,559 @@
+#![warn(rust_2018_idioms, unreachable_pub)]
+#![forbid(unsafe_code)]
+
+//! This crate provides the [`SExpr`] derive macro for `serde_kicad_sexpr`. Use it
+//! through the `derive` feature of that crate instead of depending on it directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+	parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Error, Fields,
+	GenericParam, Lifetime, LifetimeParam, LitStr, Type
+};
+
+/// Derive `Serialize` and `Deserialize` for a struct that is written as an s-expr.
+///
+/// The struct is written as an s-expr named like the struct, and its fields are
+/// written in order. The layout is described with `#[sexpr(..)]` attributes:
+///
+///  - `#[sexpr(name = "pad")]` on the struct or a field changes its name.
+///  - `#[sexpr(positional)]` marks a field as a plain value at its position. This is
+///    the default for all fields except booleans.
+///  - `#[sexpr(flag)]` marks a boolean that is written as a bare symbol named like
+///    the field if it is true, and omitted otherwise.
+///  - `#[sexpr(children, name = "pad")]` marks a collection that holds all children
+///    with that name, even if they are interleaved with other children.
+///  - `#[sexpr(rest)]` marks a collection that holds all remaining children of the
+///    s-expr. This has to be the last field.
+///  - `#[sexpr(optional)]` marks an [`Option`] whose value is an s-expr that is
+///    named differently than the field. Options in tuple structs need this, too.
+///  - `#[sexpr(extra)]` marks an `Extra` field that keeps unknown children. This has
+///    to be the first field.
+///
+/// Layouts that cannot be written, like a boolean in a tuple struct or a field after
+/// the `rest` field, are a compile error.
+///
+/// ### Example
+///
+/// ```rust
+/// use serde_kicad_sexpr::SExpr;
+///
+/// #[derive(SExpr)]
+/// #[sexpr(name = "xy")]
+/// struct Point(f32, f32);
+///
+/// #[derive(SExpr)]
+/// #[sexpr(name = "thickness")]
+/// struct Thickness(f32);
+///
+/// #[derive(SExpr)]
+/// #[sexpr(name = "polyline")]
+/// struct Polyline {
+///     #[sexpr(optional)]
+///     thickness: Option<Thickness>,
+///     #[sexpr(flag)]
+///     locked: bool,
+///     #[sexpr(rest)]
+///     points: Vec<Point>
+/// }
+///
+/// let input = "(polyline locked (xy 0 0) (xy 1 1))";
+/// let line: Polyline = serde_kicad_sexpr::from_str(input).unwrap();
+/// assert!(line.thickness.is_none() && line.locked);
+/// assert_eq!(serde_kicad_sexpr::to_string(&line).unwrap(), input);
+/// ```
+#[proc_macro_derive(SExpr, attributes(sexpr))]
+pub fn derive_sexpr(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	expand(input)
+		.unwrap_or_else(Error::into_compile_error)
+		.into()
+}
+
+/// The layout of a single field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Layout {
+	Positional,
+	Flag,
+	Children,
+	Rest,
+	Optional,
+	Extra
+}
+
+impl Layout {
+	fn attr(self) -> &'static str {
+		match self {
+			Self::Positional => "positional",
+			Self::Flag => "flag",
+			Self::Children => "children",
+			Self::Rest => "rest",
+			Self::Optional => "optional",
+			Self::Extra => "extra"
+		}
+	}
+}
+
+/// The content of all `#[sexpr(..)]` attributes of a struct or field.
+#[derive(Default)]
+struct Attrs {
+	name: Option<LitStr>,
+	layout: Option<(Layout, Span)>
+}
+
+impl Attrs {
+	fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+		let mut this = Self::default();
+		for attr in attrs.iter().filter(|attr| attr.path().is_ident("sexpr")) {
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("name") {
+					if this.name.is_some() {
+						return Err(meta.error("duplicate `name` attribute"));
+					}
+					this.name = Some(meta.value()?.parse()?);
+					return Ok(());
+				}
+
+				let layout = [
+					Layout::Positional,
+					Layout::Flag,
+					Layout::Children,
+					Layout::Rest,
+					Layout::Optional,
+					Layout::Extra
+				]
+				.into_iter()
+				.find(|layout| meta.path.is_ident(layout.attr()))
+				.ok_or_else(|| meta.error("unknown sexpr attribute"))?;
+				if let Some((other, _)) = this.layout {
+					return Err(meta.error(format_args!(
+						"`{}` cannot be combined with `{}`",
+						layout.attr(),
+						other.attr()
+					)));
+				}
+				this.layout = Some((layout, meta.path.span()));
+				Ok(())
+			})?;
+		}
+		Ok(this)
+	}
+}
+
+/// Return the name of the outermost type, i.e. `Vec` for `Vec<T>`.
+fn type_name(ty: &Type) -> Option<String> {
+	match ty {
+		Type::Path(path) => path
+			.path
+			.segments
+			.last()
+			.map(|segment| segment.ident.to_string()),
+		Type::Group(group) => type_name(&group.elem),
+		Type::Paren(paren) => type_name(&paren.elem),
+		_ => None
+	}
+}
+
+/// Return true if the type needs a name to be written, i.e. it is a boolean or a
+/// sequence.
+fn needs_name(ty: &Type) -> bool {
+	match ty {
+		Type::Tuple(tuple) => !tuple.elems.is_empty(),
+		Type::Array(_) | Type::Slice(_) => true,
+		ty => matches!(type_name(ty).as_deref(), Some("bool" | "Vec"))
+	}
+}
+
+/// Collects all errors instead of stopping at the first one.
+#[derive(Default)]
+struct Errors(Option<Error>);
+
+impl Errors {
+	fn push<T: ToTokens, M: std::fmt::Display>(&mut self, tokens: T, msg: M) {
+		self.push_err(Error::new_spanned(tokens, msg));
+	}
+
+	fn push_span<M: std::fmt::Display>(&mut self, span: Span, msg: M) {
+		self.push_err(Error::new(span, msg));
+	}
+
+	fn push_err(&mut self, err: Error) {
+		match &mut self.0 {
+			Some(errors) => errors.combine(err),
+			None => self.0 = Some(err)
+		}
+	}
+
+	fn finish(self) -> syn::Result<()> {
+		self.0.map_or(Ok(()), Err)
+	}
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+	let data = match &input.data {
+		Data::Struct(data) => data,
+		_ => {
+			return Err(Error::new_spanned(
+				&input.ident,
+				"SExpr can only be derived for structs, use the untagged! or \
+				 tagged! macro for enums"
+			));
+		}
+	};
+
+	let attrs = Attrs::parse(&input.attrs)?;
+	let mut errors = Errors::default();
+	if let Some((layout, span)) = attrs.layout {
+		errors.push_span(span, format_args!(
+			"`{}` can only be used on fields",
+			layout.attr()
+		));
+	}
+
+	let named = matches!(data.fields, Fields::Named(_));
+	let mut rest = None;
+	let mut fields = Vec::new();
+	for (index, field) in data.fields.iter().enumerate() {
+		let field_attrs = match Attrs::parse(&field.attrs) {
+			Ok(field_attrs) => field_attrs,
+			Err(err) => {
+				errors.push_err(err);
+				continue;
+			}
+		};
+		let ty = &field.ty;
+		let is = |name: &str| type_name(ty).as_deref() == Some(name);
+
+		if let Some(rest) = &rest {
+			errors.push(
+				field,
+				format_args!("field after the `rest` field `{rest}` is never read")
+			);
+		}
+
+		let layout = field_attrs.layout.map(|(layout, _)| layout);
+		let mut serde = Vec::new();
+		if let Some(name) = &field_attrs.name {
+			if !named {
+				errors.push(name, "fields of tuple structs have no name");
+			}
+			if layout != Some(Layout::Children) {
+				serde.push(quote!(rename = #name));
+			}
+		}
+
+		if !named && needs_name(ty) {
+			errors.push(
+				ty,
+				"booleans and sequences need a name, so they cannot appear in \
+				 tuple structs"
+			);
+		}
+		if let Some((layout, span)) = field_attrs.layout {
+			let named_only = matches!(
+				layout,
+				Layout::Flag | Layout::Children | Layout::Rest | Layout::Extra
+			);
+			if !named && named_only {
+				errors.push_span(span, format_args!(
+					"`{}` can only be used in structs with named fields",
+					layout.attr()
+				));
+			}
+			let nameless = matches!(layout, Layout::Rest | Layout::Extra);
+			if nameless && field_attrs.name.is_some() {
+				errors.push_span(span, format_args!(
+					"`{}` fields have no name",
+					layout.attr()
+				));
+			}
+		}
+
+		if !named && is("Option") && layout != Some(Layout::Optional) {
+			errors.push(
+				ty,
+				"options in tuple structs cannot be told apart from the next value, \
+				 use `#[sexpr(optional)]`"
+			);
+		}
+
+		match layout {
+			Some(Layout::Positional) if needs_name(ty) => {
+				errors.push(ty, "booleans and sequences cannot be positional");
+			},
+			Some(Layout::Flag) => {
+				if !is("bool") {
+					errors.push(ty, "only booleans can be a flag");
+				}
+				serde.push(quote!(default));
+			},
+			Some(Layout::Children) => {
+				if !is("Vec") {
+					errors.push(ty, "only a Vec can hold children");
+				}
+				match &field_attrs.name {
+					Some(name) => {
+						let collection = format!("{}*", name.value());
+						let name = LitStr::new(&collection, name.span());
+						serde.push(quote!(default, rename = #name));
+					},
+					None => {
+						let msg = "`children` needs the name of the children";
+						errors.push(field, msg);
+					}
+				}
+			},
+			Some(Layout::Rest) => {
+				if !is("Vec") {
+					errors.push(ty, "only a Vec can hold the remaining children");
+				}
+				rest = Some(match &field.ident {
+					Some(ident) => ident.to_string(),
+					None => index.to_string()
+				});
+				serde.push(quote!(default, rename = ""));
+			},
+			Some(Layout::Optional) => {
+				if !is("Option") {
+					errors.push(ty, "only an Option can be optional");
+				}
+				serde.push(quote!(default, with = "::serde_kicad_sexpr::Option"));
+			},
+			Some(Layout::Extra) => {
+				if !is("Extra") {
+					errors.push(ty, "only an Extra can keep unknown children");
+				}
+				if index != 0 {
+					errors.push(field, "the `extra` field has to come first");
+				}
+				serde.push(quote!(default, rename = "$extra"));
+			},
+			Some(Layout::Positional) | None => {}
+		}
+		let vis = &field.vis;
+		let ident = &field.ident;
+		let colon = field.colon_token;
+		fields.push(quote! {
+			#[serde(#(#serde),*)]
+			#vis #ident #colon #ty
+		});
+	}
+	errors.finish()?;
+
+	let ident = &input.ident;
+	let shadow = format_ident!("__SExpr{}", ident);
+	let name = match attrs.name {
+		Some(name) => name,
+		None => LitStr::new(&ident.to_string(), ident.span())
+	};
+	let remote = LitStr::new(&ident.to_string(), ident.span());
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+	let params = &input.generics.params;
+	let body = match &data.fields {
+		Fields::Named(_) => quote!(#where_clause { #(#fields),* }),
+		Fields::Unnamed(_) => quote!((#(#fields),*) #where_clause;),
+		Fields::Unit => quote!(#where_clause;)
+	};
+
+	let type_params: Vec<_> = input
+		.generics
+		.type_params()
+		.map(|param| &param.ident)
+		.collect();
+	let where_preds = where_clause.map(|clause| &clause.predicates);
+
+	// the input has to outlive every lifetime of the type, so that they can borrow
+	// from it
+	let mut de_generics = input.generics.clone();
+	let mut de = LifetimeParam::new(Lifetime::new("'de", Span::call_site()));
+	de.bounds
+		.extend(input.generics.lifetimes().map(|param| param.lifetime.clone()));
+	de_generics.params.insert(0, GenericParam::Lifetime(de));
+	let (de_impl_generics, _, _) = de_generics.split_for_impl();
+
+	let serde = quote!(::serde_kicad_sexpr::private::serde);
+	Ok(quote! {
+		#[doc(hidden)]
+		const _: () = {
+			#[allow(dead_code, non_camel_case_types)]
+			#[derive(#serde::Serialize, #serde::Deserialize)]
+			#[serde(
+				crate = "::serde_kicad_sexpr::private::serde",
+				remote = #remote,
+				rename = #name
+			)]
+			struct #shadow<#params> #body
+
+			impl #impl_generics #serde::Serialize for #ident #ty_generics
+			where
+				#(#type_params: #serde::Serialize,)*
+				#where_preds
+			{
+				fn serialize<__S>(
+					&self,
+					serializer: __S
+				) -> ::std::result::Result<__S::Ok, __S::Error>
+				where
+					__S: #serde::Serializer
+				{
+					#shadow::serialize(self, serializer)
+				}
+			}
+
+			impl #de_impl_generics #serde::Deserialize<'de> for #ident #ty_generics
+			where
+				#(#type_params: #serde::Deserialize<'de>,)*
+				#where_preds
+			{
+				fn deserialize<__D>(
+					deserializer: __D
+				) -> ::std::result::Result<Self, __D::Error>
+				where
+					__D: #serde::Deserializer<'de>
+				{
+					#shadow::deserialize(deserializer)
+				}
+			}
+		};
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::expand;
+	use syn::{parse_quote, DeriveInput};
+
+	fn errors(input: DeriveInput) -> Vec<String> {
+		match expand(input) {
+			Ok(_) => Vec::new(),
+			Err(err) => err.into_iter().map(|err| err.to_string()).collect()
+		}
+	}
+
+	#[test]
+	fn unnamed_boolean() {
+		let errors = errors(parse_quote! {
+			struct Pad(String, bool);
+		});
+		assert_eq!(errors, [
+			"booleans and sequences need a name, so they cannot appear in tuple structs"
+		]);
+	}
+
+	#[test]
+	fn unnamed_option() {
+		let errors = errors(parse_quote! {
+			struct Pad(String, Option<u32>, #[sexpr(optional)] Option<Size>);
+		});
+		assert_eq!(errors, [
+			"options in tuple structs cannot be told apart from the next value, use \
+			 `#[sexpr(optional)]`"
+		]);
+	}
+
+	#[test]
+	fn field_after_rest() {
+		let errors = errors(parse_quote! {
+			struct Polygon {
+				#[sexpr(rest)]
+				points: Vec<Point>,
+				width: f32,
+				#[sexpr(flag)]
+				locked: bool
+			}
+		});
+		assert_eq!(errors, [
+			"field after the `rest` field `points` is never read",
+			"field after the `rest` field `points` is never read"
+		]);
+	}
+
+	#[test]
+	fn rest_not_vec() {
+		let errors = errors(parse_quote! {
+			struct Polygon {
+				#[sexpr(rest)]
+				points: u32
+			}
+		});
+		assert_eq!(errors, ["only a Vec can hold the remaining children"]);
+	}
+
+	#[test]
+	fn extra_not_extra() {
+		let errors = errors(parse_quote! {
+			struct Footprint {
+				#[sexpr(extra)]
+				extra: Vec<String>
+			}
+		});
+		assert_eq!(errors, ["only an Extra can keep unknown children"]);
+	}
+
+	#[test]
+	fn wrong_types() {
+		let errors = errors(parse_quote! {
+			struct Text {
+				#[sexpr(flag)]
+				hide: String,
+				#[sexpr(optional)]
+				effects: Effects,
+				#[sexpr(positional)]
+				layers: Vec<String>
+			}
+		});
+		assert_eq!(errors, [
+			"only booleans can be a flag",
+			"only an Option can be optional",
+			"booleans and sequences cannot be positional"
+		]);
+	}
+
+	#[test]
+	fn misplaced_attributes() {
+		let errors = errors(parse_quote! {
+			#[sexpr(rest)]
+			struct Text(#[sexpr(name = "content")] String, #[sexpr(flag)] bool);
+		});
+		assert_eq!(errors, [
+			"`rest` can only be used on fields",
+			"fields of tuple structs have no name",
+			"booleans and sequences need a name, so they cannot appear in tuple structs",
+			"`flag` can only be used in structs with named fields"
+		]);
+	}
+
+	#[test]
+	fn children_without_name() {
+		let errors = errors(parse_quote! {
+			struct Footprint {
+				#[sexpr(children)]
+				pads: Vec<Pad>
+			}
+		});
+		assert_eq!(errors, ["`children` needs the name of the children"]);
+	}
+
+	#[test]
+	fn conflicting_attributes() {
+		let errors = errors(parse_quote! {
+			struct Text {
+				#[sexpr(flag, rest)]
+				hide: bool
+			}
+		});
+		assert_eq!(errors, ["`rest` cannot be combined with `flag`"]);
+	}
+
+	#[test]
+	fn enum_unsupported() {
+		let errors = errors(parse_quote! {
+			enum Shape {
+				Line(Line)
+			}
+		});
+		assert_eq!(errors.len(), 1);
+		assert!(errors[0].starts_with("SExpr can only be derived for structs"));
+	}
+}
@@ -0,0 +1,160 @@
+use serde::de::DeserializeOwned;
+use serde_kicad_sexpr::{Extra, SExpr};
+use std::fmt::Debug;
+
+fn assert_round_trip<T>(input: &str, expected: &T)
+where
+	T: Debug + DeserializeOwned + PartialEq + serde::Serialize
+{
+	let parsed: T =
+		serde_kicad_sexpr::from_str(input).expect("Failed to parse input");
+	assert_eq!(&parsed, expected);
+	let written =
+		serde_kicad_sexpr::to_string(&parsed).expect("Failed to write input");
+	assert_eq!(written, input);
+}
+
+#[derive(Debug, PartialEq, SExpr)]
+#[sexpr(name = "xy")]
+struct Point(f32, f32);
+
+#[derive(Debug, PartialEq, SExpr)]
+#[sexpr(name = "width")]
+struct Width(f32);
+
+#[derive(Debug, PartialEq, SExpr)]
+#[sexpr(name = "pts")]
+struct Points {
+	#[sexpr(rest)]
+	points: Vec<Point>
+}
+
+#[derive(Debug, PartialEq, SExpr)]
+#[sexpr(name = "gr_poly")]
+struct Polygon {
+	#[sexpr(extra)]
+	extra: Extra,
+	pts: Points,
+	#[sexpr(optional)]
+	width: Option<Width>,
+	#[sexpr(flag, name = "locked")]
+	is_locked: bool,
+	#[sexpr(positional)]
+	layer: String
+}
+
+#[test]
+fn polygon() {
+	assert_round_trip(
+		r#"(gr_poly (pts (xy 0 0) (xy 1 1)) locked "F.Cu")"#,
+		&Polygon {
+			extra: Extra::new(),
+			pts: Points {
+				points: vec![Point(0.0, 0.0), Point(1.0, 1.0)]
+			},
+			width: None,
+			is_locked: true,
+			layer: "F.Cu".to_owned()
+		}
+	);
+}
+
+#[test]
+fn polygon_width() {
+	assert_round_trip(
+		r#"(gr_poly (pts) (width 0.1) "F.Cu")"#,
+		&Polygon {
+			extra: Extra::new(),
+			pts: Points { points: Vec::new() },
+			width: Some(Width(0.1)),
+			is_locked: false,
+			layer: "F.Cu".to_owned()
+		}
+	);
+}
+
+#[derive(Debug, PartialEq, SExpr)]
+#[sexpr(name = "pad")]
+struct Pad(String);
+
+#[derive(Debug, PartialEq, SExpr)]
+#[sexpr(name = "footprint")]
+struct Footprint {
+	#[sexpr(extra)]
+	extra: Extra,
+	#[sexpr(positional)]
+	name: String,
+	#[sexpr(children, name = "gr_poly")]
+	polygons: Vec<Polygon>,
+	#[sexpr(children, name = "pad")]
+	pads: Vec<Pad>
+}
+
+#[test]
+fn interleaved_children() {
+	let input = r#"(footprint "R1" (pad "1") (gr_poly (pts) "F.Cu") (pad "2"))"#;
+	let mut parsed: Footprint =
+		serde_kicad_sexpr::from_str(input).expect("Failed to parse input");
+	assert_eq!(parsed.name, "R1");
+	assert_eq!(parsed.polygons, vec![Polygon {
+		extra: Extra::new(),
+		pts: Points { points: Vec::new() },
+		width: None,
+		is_locked: false,
+		layer: "F.Cu".to_owned()
+	}]);
+	assert_eq!(parsed.pads, vec![Pad("1".to_owned()), Pad("2".to_owned())]);
+	let written =
+		serde_kicad_sexpr::to_string(&parsed).expect("Failed to write input");
+	assert_eq!(written, input);
+
+	// children that were added are written after the others
+	parsed.pads.push(Pad("3".to_owned()));
+	let written =
+		serde_kicad_sexpr::to_string(&parsed).expect("Failed to write input");
+	assert_eq!(
+		written,
+		r#"(footprint "R1" (pad "1") (gr_poly (pts) "F.Cu") (pad "2") (pad "3"))"#
+	);
+}
+
+#[derive(Debug, PartialEq, SExpr)]
+struct Unit;
+
+#[test]
+fn unit_struct() {
+	assert_round_trip("(Unit)", &Unit);
+}
+
+#[derive(Debug, PartialEq, SExpr)]
+#[sexpr(name = "pair")]
+struct Pair<T = f32>(T, T);
+
+#[test]
+fn generic_struct() {
+	assert_round_trip("(pair 1 2)", &Pair(1, 2));
+	assert_round_trip::<Pair>("(pair 1.5 2)", &Pair(1.5, 2.0));
+}
+
+#[derive(Debug, PartialEq, SExpr)]
+#[sexpr(name = "net")]
+struct Net<'a> {
+	#[sexpr(positional)]
+	code: u32,
+	#[sexpr(positional)]
+	name: &'a str
+}
+
+#[test]
+fn borrowed_struct() {
+	let input = r#"(net 1 "Net-(R1-Pad1)")"#;
+	let parsed: Net<'_> =
+		serde_kicad_sexpr::from_str(input).expect("Failed to parse input");
+	assert_eq!(parsed, Net {
+		code: 1,
+		name: "Net-(R1-Pad1)"
+	});
+	let written =
+		serde_kicad_sexpr::to_string(&parsed).expect("Failed to write input");
+	assert_eq!(written, input);
+}
@@ -0,0 +1,610 @@
+//! A lossless concrete syntax tree for s-expressions.
+//!
+//! Unlike the [`Deserializer`](crate::de::Deserializer), which throws away all formatting,
+//! this module keeps the original whitespace, comments and spelling of every token. An
+//! untouched [`Document`] is written back byte-for-byte, and editing a node only changes
+//! the bytes of that node:
+//!
+//! ```rust
+//! use serde_kicad_sexpr::cst::Document;
+//!
+//! let input = "(kicad_sch (version 20211123)\n  (at 1.0   2))\n";
+//! let mut doc = Document::parse(input).unwrap();
+//! assert_eq!(doc.to_string(), input);
+//!
+//! let at = doc.root_mut().unwrap().get_mut("at").unwrap();
+//! at.args_mut()[1].as_atom_mut().unwrap().set_f64(2.54);
+//! assert_eq!(doc.to_string(), "(kicad_sch (version 20211123)\n  (at 1.0   2.54))\n");
+//! ```
+//!
+//! Comments are lines whose first non-whitespace character is `#`. They are kept as
+//! part of the whitespace in front of the next token.
+
+use crate::{
+	de::{unescape, Error, ErrorKind, Span},
+	ser::{escape, format_exact, needs_quotes, FloatFormat},
+	Value
+};
+use std::{
+	borrow::Cow,
+	fmt::{self, Display, Formatter}
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// A parsed s-expression document, e.g. the content of a `.kicad_sch` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document<'a> {
+	/// The top-level nodes of the document. Usually this is just one s-expr.
+	pub nodes: Vec<Node<'a>>,
+
+	/// The whitespace and comments after the last node.
+	pub trailing: Cow<'a, str>
+}
+
+impl<'a> Document<'a> {
+	/// Parse a document. The returned tree borrows all of its text from `input`.
+	pub fn parse(input: &'a str) -> Result<Self> {
+		let mut parser = Parser { input, pos: 0 };
+		let mut nodes = Vec::new();
+		loop {
+			let leading = parser.trivia();
+			match parser.peek() {
+				None => {
+					return Ok(Self {
+						nodes,
+						trailing: leading.into()
+					})
+				},
+				Some(')') => return Err(parser.error(ErrorKind::ExpectedSExpr(')'), 1)),
+				Some(_) => nodes.push(parser.node(leading)?)
+			}
+		}
+	}
+
+	/// Return the first top-level s-expr.
+	pub fn root(&self) -> Option<&List<'a>> {
+		self.nodes.iter().find_map(Node::as_list)
+	}
+
+	/// Return the first top-level s-expr.
+	pub fn root_mut(&mut self) -> Option<&mut List<'a>> {
+		self.nodes.iter_mut().find_map(Node::as_list_mut)
+	}
+
+	/// Copy all borrowed text so that the document no longer borrows the input.
+	pub fn into_owned(self) -> Document<'static> {
+		Document {
+			nodes: self.nodes.into_iter().map(Node::into_owned).collect(),
+			trailing: owned(self.trailing)
+		}
+	}
+}
+
+impl Display for Document<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		for node in &self.nodes {
+			Display::fmt(node, f)?;
+		}
+		f.write_str(&self.trailing)
+	}
+}
+
+/// A single node of the syntax tree, i.e. either a token or an s-expr.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node<'a> {
+	Atom(Atom<'a>),
+	List(List<'a>)
+}
+
+impl<'a> Node<'a> {
+	/// Return the whitespace and comments in front of this node.
+	pub fn leading(&self) -> &str {
+		match self {
+			Self::Atom(atom) => &atom.leading,
+			Self::List(list) => &list.leading
+		}
+	}
+
+	/// Replace the whitespace and comments in front of this node.
+	pub fn set_leading(&mut self, leading: impl Into<Cow<'a, str>>) {
+		match self {
+			Self::Atom(atom) => atom.leading = leading.into(),
+			Self::List(list) => list.leading = leading.into()
+		}
+	}
+
+	pub fn as_atom(&self) -> Option<&Atom<'a>> {
+		match self {
+			Self::Atom(atom) => Some(atom),
+			Self::List(_) => None
+		}
+	}
+
+	pub fn as_atom_mut(&mut self) -> Option<&mut Atom<'a>> {
+		match self {
+			Self::Atom(atom) => Some(atom),
+			Self::List(_) => None
+		}
+	}
+
+	pub fn as_list(&self) -> Option<&List<'a>> {
+		match self {
+			Self::Atom(_) => None,
+			Self::List(list) => Some(list)
+		}
+	}
+
+	pub fn as_list_mut(&mut self) -> Option<&mut List<'a>> {
+		match self {
+			Self::Atom(_) => None,
+			Self::List(list) => Some(list)
+		}
+	}
+
+	/// Copy all borrowed text so that the node no longer borrows the input.
+	pub fn into_owned(self) -> Node<'static> {
+		match self {
+			Self::Atom(atom) => Node::Atom(atom.into_owned()),
+			Self::List(list) => Node::List(list.into_owned())
+		}
+	}
+}
+
+impl Display for Node<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Atom(atom) => Display::fmt(atom, f),
+			Self::List(list) => Display::fmt(list, f)
+		}
+	}
+}
+
+/// Create a new node from a [`Value`]. All tokens are separated by a single space.
+impl From<&Value> for Node<'static> {
+	fn from(value: &Value) -> Self {
+		match value {
+			Value::List(head, items) => Node::List(List {
+				leading: Cow::Borrowed(""),
+				items: std::iter::once(Node::Atom(Atom::symbol(head)))
+					.chain(items.iter().map(|item| {
+						let mut node = Node::from(item);
+						node.set_leading(" ");
+						node
+					}))
+					.collect(),
+				trailing: Cow::Borrowed("")
+			}),
+			Value::Symbol(symbol) => Node::Atom(Atom::symbol(symbol)),
+			Value::String(string) => Node::Atom(Atom::string(string)),
+			Value::Int(int) => Node::Atom(Atom::raw(int.to_string())),
+			Value::Float(float) => Node::Atom(Atom::raw(format_exact(*float))),
+		}
+	}
+}
+
+/// A single token, like a symbol, a quoted string or a number.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Atom<'a> {
+	leading: Cow<'a, str>,
+	raw: Cow<'a, str>
+}
+
+impl<'a> Atom<'a> {
+	/// Create a token from its raw spelling, which is not checked.
+	pub fn raw(raw: impl Into<Cow<'a, str>>) -> Self {
+		Self {
+			leading: Cow::Borrowed(""),
+			raw: raw.into()
+		}
+	}
+
+	/// Create a symbol, which is only quoted if necessary.
+	pub fn symbol(symbol: &str) -> Self {
+		match needs_quotes(symbol, false) {
+			true => Self::string(symbol),
+			false => Self::raw(symbol.to_owned())
+		}
+	}
+
+	/// Create a quoted string.
+	pub fn string(string: &str) -> Self {
+		Self::raw(format!(r#""{}""#, escape(string)))
+	}
+
+	/// Return the token exactly as it appears in the input, including quotes.
+	pub fn raw_str(&self) -> &str {
+		&self.raw
+	}
+
+	/// Return true if this token is a quoted string.
+	pub fn is_quoted(&self) -> bool {
+		self.raw.starts_with('"')
+	}
+
+	/// Return the value of this token, with quotes and escapes removed.
+	pub fn as_str(&self) -> Cow<'_, str> {
+		if !self.is_quoted() {
+			return Cow::Borrowed(&self.raw);
+		}
+		let inner = &self.raw[1..self.raw.len() - 1];
+		if !inner.contains('\\') {
+			return Cow::Borrowed(inner);
+		}
+		let value = unescape(inner).unwrap_or_else(|err| {
+			String::from_utf8_lossy(err.as_bytes()).into_owned()
+		});
+		Cow::Owned(value)
+	}
+
+	/// Parse this token as an integer.
+	pub fn as_i64(&self) -> Option<i64> {
+		self.raw.parse().ok()
+	}
+
+	/// Parse this token as a floating point number.
+	pub fn as_f64(&self) -> Option<f64> {
+		self.raw.parse().ok()
+	}
+
+	/// Replace the raw spelling of this token, which is not checked.
+	pub fn set_raw(&mut self, raw: impl Into<Cow<'a, str>>) {
+		self.raw = raw.into();
+	}
+
+	/// Replace the value of this token. A quoted string stays quoted, a symbol is
+	/// only quoted if necessary.
+	pub fn set_str(&mut self, value: &str) {
+		self.raw = match self.is_quoted() {
+			true => Self::string(value).raw,
+			false => Self::symbol(value).raw
+		};
+	}
+
+	/// Replace the value of this token with an integer.
+	pub fn set_i64(&mut self, value: i64) {
+		self.raw = Cow::Owned(value.to_string());
+	}
+
+	/// Replace the value of this token with a floating point number, written like
+	/// the [`Serializer`](crate::ser::Serializer) does by default. This rounds to
+	/// six decimals, use [`set_f64_with_format`](Self::set_f64_with_format) with
+	/// [`FloatFormat::shortest`] to keep all digits.
+	pub fn set_f64(&mut self, value: f64) {
+		self.set_f64_with_format(value, &FloatFormat::default());
+	}
+
+	/// Replace the value of this token with a floating point number, written using
+	/// the options from `format`.
+	pub fn set_f64_with_format(&mut self, value: f64, format: &FloatFormat) {
+		self.raw = Cow::Owned(format.format(value));
+	}
+
+	/// Copy all borrowed text so that the token no longer borrows the input.
+	pub fn into_owned(self) -> Atom<'static> {
+		Atom {
+			leading: owned(self.leading),
+			raw: owned(self.raw)
+		}
+	}
+}
+
+impl Display for Atom<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.leading)?;
+		f.write_str(&self.raw)
+	}
+}
+
+/// An s-expr like `(name values...)`. The name is the first of its items.
+#[derive(Clone, Debug, PartialEq)]
+pub struct List<'a> {
+	leading: Cow<'a, str>,
+
+	/// All nodes inside the parentheses, including the name.
+	pub items: Vec<Node<'a>>,
+
+	/// The whitespace and comments before the closing parenthesis.
+	pub trailing: Cow<'a, str>
+}
+
+impl<'a> List<'a> {
+	/// Return the name of this s-expr.
+	pub fn head(&self) -> Option<&str> {
+		self.items
+			.first()
+			.and_then(Node::as_atom)
+			.map(Atom::raw_str)
+	}
+
+	/// Return all nodes after the name.
+	pub fn args(&self) -> &[Node<'a>] {
+		self.items.get(1..).unwrap_or_default()
+	}
+
+	/// Return all nodes after the name.
+	pub fn args_mut(&mut self) -> &mut [Node<'a>] {
+		self.items.get_mut(1..).unwrap_or_default()
+	}
+
+	/// Return the first child s-expr with the given name.
+	pub fn get(&self, head: &str) -> Option<&List<'a>> {
+		self.items
+			.iter()
+			.filter_map(Node::as_list)
+			.find(|list| list.head() == Some(head))
+	}
+
+	/// Return the first child s-expr with the given name.
+	pub fn get_mut(&mut self, head: &str) -> Option<&mut List<'a>> {
+		self.items
+			.iter_mut()
+			.filter_map(Node::as_list_mut)
+			.find(|list| list.head() == Some(head))
+	}
+
+	/// Return all child s-exprs with the given name.
+	pub fn lists<'b>(&'b self, head: &'b str) -> impl Iterator<Item = &'b List<'a>> {
+		self.items
+			.iter()
+			.filter_map(Node::as_list)
+			.filter(move |list| list.head() == Some(head))
+	}
+
+	/// Append a node. If the node has no leading whitespace, it is indented like the
+	/// previous s-expr, or separated by a single space if there is none.
+	pub fn push(&mut self, mut node: Node<'a>) {
+		if node.leading().is_empty() {
+			let leading = self
+				.items
+				.iter()
+				.rev()
+				.filter(|item| matches!(item, Node::List(_)))
+				.map(|item| item.leading())
+				.find(|leading| leading.contains('\n'))
+				.map(|leading| &leading[leading.rfind('\n').unwrap()..])
+				.unwrap_or(" ")
+				.to_owned();
+			node.set_leading(leading);
+		}
+		self.items.push(node);
+	}
+
+	/// Remove and return the node at `index`.
+	pub fn remove(&mut self, index: usize) -> Node<'a> {
+		self.items.remove(index)
+	}
+
+	/// Copy all borrowed text so that the s-expr no longer borrows the input.
+	pub fn into_owned(self) -> List<'static> {
+		List {
+			leading: owned(self.leading),
+			items: self.items.into_iter().map(Node::into_owned).collect(),
+			trailing: owned(self.trailing)
+		}
+	}
+}
+
+impl Display for List<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.leading)?;
+		f.write_str("(")?;
+		for item in &self.items {
+			Display::fmt(item, f)?;
+		}
+		f.write_str(&self.trailing)?;
+		f.write_str(")")
+	}
+}
+
+fn owned(text: Cow<'_, str>) -> Cow<'static, str> {
+	Cow::Owned(text.into_owned())
+}
+
+struct Parser<'a> {
+	input: &'a str,
+	pos: usize
+}
+
+impl<'a> Parser<'a> {
+	fn rest(&self) -> &'a str {
+		&self.input[self.pos..]
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.rest().chars().next()
+	}
+
+	fn error(&self, kind: ErrorKind, len: usize) -> Error {
+		let mut err = Error::new(kind);
+		err.inner.span = Some(Span::locate(self.input, self.pos, len));
+		err
+	}
+
+	/// Check whether the current position is the first non-whitespace character
+	/// of its line.
+	fn at_line_start(&self) -> bool {
+		let line = &self.input[..self.pos];
+		let line = &line[line.rfind('\n').map(|idx| idx + 1).unwrap_or(0)..];
+		line.chars().all(|ch| ch.is_ascii_whitespace())
+	}
+
+	/// Consume whitespace and comments.
+	fn trivia(&mut self) -> &'a str {
+		let start = self.pos;
+		loop {
+			let rest = self.rest();
+			let trimmed = rest.trim_start_matches(|ch: char| ch.is_ascii_whitespace());
+			self.pos += rest.len() - trimmed.len();
+			if !trimmed.starts_with('#') || !self.at_line_start() {
+				break;
+			}
+			self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+		}
+		&self.input[start..self.pos]
+	}
+
+	fn node(&mut self, leading: &'a str) -> Result<Node<'a>> {
+		let leading = Cow::Borrowed(leading);
+		if self.peek() != Some('(') {
+			return Ok(Node::Atom(Atom {
+				leading,
+				raw: Cow::Borrowed(self.atom()?)
+			}));
+		}
+
+		let open = self.pos;
+		self.pos += 1;
+		let mut items = Vec::new();
+		loop {
+			let inner = self.trivia();
+			match self.peek() {
+				None => {
+					self.pos = open;
+					return Err(self.error(ErrorKind::Eof, 1));
+				},
+				Some(')') => {
+					self.pos += 1;
+					return Ok(Node::List(List {
+						leading,
+						items,
+						trailing: Cow::Borrowed(inner)
+					}));
+				},
+				Some(_) => items.push(self.node(inner)?)
+			}
+		}
+	}
+
+	fn atom(&mut self) -> Result<&'a str> {
+		let rest = self.rest();
+		let len = if let Some(quoted) = rest.strip_prefix('"') {
+			let mut escaped = false;
+			let end = quoted.find(|ch| match ch {
+				_ if escaped => {
+					escaped = false;
+					false
+				},
+				'\\' => {
+					escaped = true;
+					false
+				},
+				ch => ch == '"'
+			});
+			match end {
+				Some(end) => end + 2,
+				None => return Err(self.error(ErrorKind::Eof, rest.len()))
+			}
+		} else {
+			rest.find(|ch: char| ch.is_ascii_whitespace() || "()\"".contains(ch))
+				.unwrap_or(rest.len())
+		};
+		self.pos += len;
+		Ok(&rest[..len])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Atom, Document, Node};
+	use crate::Value;
+	use std::borrow::Cow;
+
+	const INPUT: &str = r#"# generated by hand
+(kicad_sch (version 20211123) (generator eeschema)
+
+  (lib_symbols
+    (symbol "Device:R" (pin_numbers hide) (in_bom yes)
+      (property "Reference" "R" (id 0) (at 2.032 0 90)
+        (effects (font (size 1.27 1.27)))
+      )
+    )
+  )
+  # a comment between nodes
+  (junction (at 1.0 -2.54) (diameter 0) (color 0 0 0 0))
+	(text "a \"quoted\" \\ text" (at 1 2 0))
+)
+"#;
+
+	#[test]
+	fn round_trip() {
+		let doc = Document::parse(INPUT).unwrap();
+		assert_eq!(doc.to_string(), INPUT);
+		assert_eq!(doc.clone().into_owned().to_string(), INPUT);
+	}
+
+	#[test]
+	fn borrowed() {
+		let doc = Document::parse(INPUT).unwrap();
+		let version = doc.root().unwrap().get("version").unwrap();
+		let atom = version.args()[0].as_atom().unwrap();
+		assert!(matches!(atom.raw, Cow::Borrowed(_)));
+		assert_eq!(atom.as_i64(), Some(20211123));
+	}
+
+	#[test]
+	fn tokens() {
+		let doc = Document::parse(INPUT).unwrap();
+		let root = doc.root().unwrap();
+		assert_eq!(root.head(), Some("kicad_sch"));
+
+		let at = root.get("junction").unwrap().get("at").unwrap();
+		assert_eq!(at.args()[0].as_atom().unwrap().raw_str(), "1.0");
+		assert_eq!(at.args()[0].as_atom().unwrap().as_f64(), Some(1.0));
+
+		let text = root.get("text").unwrap().args()[0].as_atom().unwrap();
+		assert!(text.is_quoted());
+		assert_eq!(text.as_str(), r#"a "quoted" \ text"#);
+	}
+
+	#[test]
+	fn edit() {
+		let mut doc = Document::parse(INPUT).unwrap();
+		let root = doc.root_mut().unwrap();
+		let junction = root.get_mut("junction").unwrap();
+		junction.get_mut("at").unwrap().args_mut()[1]
+			.as_atom_mut()
+			.unwrap()
+			.set_f64(2.54 * 2.0 + 1e-9);
+		junction.get_mut("diameter").unwrap().args_mut()[0]
+			.as_atom_mut()
+			.unwrap()
+			.set_i64(1);
+		let text = root.get_mut("text").unwrap();
+		text.args_mut()[0].as_atom_mut().unwrap().set_str("new text");
+		root.push(Node::from(&Value::List("wire".to_owned(), vec![
+			Value::Symbol("yes".to_owned()),
+			Value::Float(5.080000001),
+			Value::Float(-0.0)
+		])));
+
+		let expected = INPUT
+			.replace("(at 1.0 -2.54) (diameter 0)", "(at 1.0 5.08) (diameter 1)")
+			.replace(r#""a \"quoted\" \\ text""#, r#""new text""#)
+			.replace(
+				"(at 1 2 0))\n)",
+				"(at 1 2 0))\n\t(wire yes 5.080000001 -0.0)\n)"
+			);
+		assert_eq!(doc.to_string(), expected);
+	}
+
+	#[test]
+	fn new_atoms() {
+		assert_eq!(Atom::symbol("F.Cu").raw_str(), "F.Cu");
+		assert_eq!(Atom::symbol("a b").raw_str(), r#""a b""#);
+		assert_eq!(Atom::string("R").raw_str(), r#""R""#);
+
+		let multiline = Atom::string("Line 1\nLine 2");
+		assert_eq!(multiline.raw_str(), r#""Line 1\nLine 2""#);
+		assert_eq!(multiline.as_str(), "Line 1\nLine 2");
+	}
+
+	#[test]
+	fn errors() {
+		let err = Document::parse("(a (b)").unwrap_err();
+		assert_eq!(err.position().unwrap().column, 1);
+		let err = Document::parse("(a))").unwrap_err();
+		assert_eq!(err.position().unwrap().column, 4);
+		Document::parse(r#"(a "b)"#).unwrap_err();
+	}
+}
@@ -1,8 +1,12 @@
 use serde::de;
-use std::fmt::{self, Debug, Display, Formatter};
+use std::{
+	fmt::{self, Debug, Display, Formatter},
+	io, mem,
+	sync::Arc
+};
 use thiserror::Error;
 
-#[derive(Debug, Clone, Error, PartialEq)]
+#[derive(Debug, Clone, Error)]
 pub enum ErrorKind {
 	#[error("{0}")]
 	Message(String),
@@ -41,52 +45,295 @@ pub enum ErrorKind {
 	#[error("Expected number")]
 	ExpectedNumber,
 
+	/// This error will be returned if a float is NaN or infinite, and the
+	/// deserializer only accepts finite floats.
+	#[error("Non-finite float")]
+	NonFiniteFloat,
+
 	/// This error will be returned if a string was expected, but some other token
 	/// was found.
 	#[error("Expected string")]
 	ExpectedString,
 
-	/// This error will be returned if an option was requested. [`Option`] is
-	/// **not supported** by this data format.
+	/// This error used to be returned if an option was requested. Plain
+	/// [`Option`] fields are supported now, so it is no longer returned.
+	#[deprecated(note = "Option is supported and this error is no longer returned")]
 	#[error("std::option::Option cannot be deserialized by this data format")]
 	DeserializeOption,
 
 	/// This error will be returned if an s-expr is found, but its name (and fields)
-	/// were not supplied to the deserializer (e.g. `deserialize_any` was called).
+	/// were not supplied to the deserializer (e.g. a sequence in an unnamed field).
 	#[error("Missing s-expr type info for {0}")]
 	MissingSExprInfo(String),
 
-	/// This error will be returned when attempting to deserialize a non-newtype
-	/// enum variant in a tagged way.
+	/// This error used to be returned when attempting to deserialize a non-newtype
+	/// enum variant in a tagged way. Tuple and struct variants are supported now.
+	#[deprecated(note = "Tuple and struct variants are supported now, see \
+	                     UnitVariantInSExpr for the remaining unsupported case")]
 	#[error("Non-newtype enum variants are not supported in tagged format")]
 	NonNewtypeEnumVariant,
 
+	/// This error will be returned when attempting to deserialize a unit enum
+	/// variant from an s-expr.
+	#[error("Unit enum variants cannot be deserialized from an s-expr")]
+	UnitVariantInSExpr,
+
 	/// This error will be returned when attempting to deserialize a non-unit enum
 	/// variant in an untagged way.
 	#[error("Non-unit enum variants are not supported in untagged format")]
 	NonUnitEnumVariant,
 
+	/// This error will be returned if the value that selects the variant of a
+	/// [`tagged!`](crate::tagged) enum could not be found.
+	#[error("Missing enum tag at {0}")]
+	MissingTag(&'static str),
+
+	/// This error will be returned if an [`Extra`](crate::Extra) is found in a field
+	/// that is not named `$extra`.
+	#[error("Extra children must be kept in a field named $extra")]
+	MisplacedExtra,
+
 	/// This error will be returned if there were trailing tokens after the deserialization
 	/// finished.
 	#[error("Trailing tokens")]
-	TrailingTokens
+	TrailingTokens,
+
+	/// This error will be returned if the input is not valid UTF-8. The error's
+	/// position points to the first invalid byte.
+	#[error("Invalid UTF-8 sequence")]
+	InvalidUtf8,
+
+	/// This error will be returned if reading the input failed.
+	#[error("I/O error: {0}")]
+	Io(#[source] Arc<io::Error>)
+}
+
+impl PartialEq for ErrorKind {
+	fn eq(&self, other: &Self) -> bool {
+		// variants with data have to be listed here, all others are equal if they
+		// are the same variant
+		match (self, other) {
+			(Self::Message(a), Self::Message(b)) => a == b,
+			(Self::ExpectedSExpr(a), Self::ExpectedSExpr(b)) => a == b,
+			(
+				Self::ExpectedSExprIdentifier(a, found_a),
+				Self::ExpectedSExprIdentifier(b, found_b)
+			) => a == b && found_a == found_b,
+			(Self::MissingSExprInfo(a), Self::MissingSExprInfo(b)) => a == b,
+			(Self::MissingTag(a), Self::MissingTag(b)) => a == b,
+			// I/O errors can't be compared, so only their kinds are
+			(Self::Io(a), Self::Io(b)) => a.kind() == b.kind(),
+			_ => mem::discriminant(self) == mem::discriminant(other)
+		}
+	}
+}
+
+/// A position in the input, e.g. where an error occured.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Position {
+	/// The byte offset from the start of the input.
+	pub offset: usize,
+
+	/// The line number, starting at 1.
+	pub line: usize,
+
+	/// The column in characters, starting at 1.
+	pub column: usize
+}
+
+impl Position {
+	/// Return the position at the start of the input.
+	const fn start() -> Self {
+		Self {
+			offset: 0,
+			line: 1,
+			column: 1
+		}
+	}
+
+	/// Return the position after reading `text`, starting at this position.
+	fn advance(mut self, text: &str) -> Self {
+		for ch in text.chars() {
+			self.offset += ch.len_utf8();
+			if ch == '\n' {
+				self.line += 1;
+				self.column = 1;
+			} else {
+				self.column += 1;
+			}
+		}
+		self
+	}
+
+	/// Locate the byte offset `offset` in `input`.
+	pub(crate) fn locate(input: &str, offset: usize) -> Self {
+		Self::start().advance(&input[..offset])
+	}
+}
+
+impl Display for Position {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}", self.line, self.column)
+	}
+}
+
+/// The range of the input that an error refers to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Span {
+	/// The position of the first character of the span.
+	pub start: Position,
+
+	/// The position after the last character of the span.
+	pub end: Position
+}
+
+impl Span {
+	/// Create a span that covers the invalid bytes of a [`Utf8Error`].
+	///
+	///  [`Utf8Error`]: std::str::Utf8Error
+	pub(crate) fn invalid_utf8(input: &[u8], err: &std::str::Utf8Error) -> Self {
+		let valid = err.valid_up_to();
+		let start = match std::str::from_utf8(&input[..valid]) {
+			Ok(valid) => Position::locate(valid, valid.len()),
+			Err(_) => unreachable!()
+		};
+		let len = err.error_len().unwrap_or(input.len() - valid);
+		let end = Position {
+			offset: start.offset + len,
+			line: start.line,
+			column: start.column + 1
+		};
+		Self { start, end }
+	}
+
+	/// Create a span of the `len` bytes starting at byte offset `offset` in `input`.
+	pub(crate) fn locate(input: &str, offset: usize, len: usize) -> Self {
+		let start = Position::locate(input, offset);
+		let end = start.advance(&input[offset..offset + len]);
+		Self { start, end }
+	}
+}
+
+/// One step of the path from the root s-expr to the value that caused an error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PathSegment {
+	/// An s-expr or a named field. If the s-expr is an element of a collection,
+	/// `index` is its position in that collection.
+	Name {
+		name: &'static str,
+		index: Option<usize>
+	},
+
+	/// A positional value inside an s-expr in tuple format.
+	Index(usize)
+}
+
+impl Display for PathSegment {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Name { name, index: None } => f.write_str(name),
+			Self::Name {
+				name,
+				index: Some(index)
+			} => write!(f, "{name}[{index}]"),
+			Self::Index(index) => write!(f, "{index}")
+		}
+	}
 }
 
 #[non_exhaustive]
 pub struct Error {
 	pub kind: ErrorKind,
 
+	/// Everything else is boxed to keep results small.
+	pub(crate) inner: Box<ErrorInner>
+}
+
+pub(crate) struct ErrorInner {
+	pub(crate) span: Option<Span>,
+
+	pub(crate) path: Vec<PathSegment>,
+
 	#[cfg(feature = "backtrace")]
-	pub(super) backtrace: once_cell::sync::Lazy<
+	backtrace: once_cell::sync::Lazy<
 		backtrace::Backtrace,
 		Box<dyn FnOnce() -> backtrace::Backtrace>
 	>
 }
 
 impl Error {
+	pub(crate) fn new(kind: ErrorKind) -> Self {
+		Self {
+			kind,
+			inner: Box::new(ErrorInner {
+				span: None,
+				path: Vec::new(),
+				#[cfg(feature = "backtrace")]
+				backtrace: {
+					let bt = backtrace::Backtrace::new_unresolved();
+					once_cell::sync::Lazy::new(Box::new(move || {
+						let mut bt = bt;
+						bt.resolve();
+						bt
+					}))
+				}
+			})
+		}
+	}
+
+	/// Return the position in the input where this error occured, if known.
+	pub fn position(&self) -> Option<Position> {
+		self.inner.span.map(|span| span.start)
+	}
+
+	/// Return the span of the input that caused this error, if known.
+	pub fn span(&self) -> Option<Span> {
+		self.inner.span
+	}
+
+	/// Return the path from the root s-expr to the value that caused this error.
+	pub fn path(&self) -> &[PathSegment] {
+		&self.inner.path
+	}
+
+	/// Record that this error occured inside the s-expr `name`.
+	pub(crate) fn within_sexpr(mut self, name: &'static str) -> Self {
+		self.inner
+			.path
+			.insert(0, PathSegment::Name { name, index: None });
+		self
+	}
+
+	/// Record that this error occured inside the field `name`. Fields that are
+	/// named like their s-expr, and the special empty field, are not recorded.
+	pub(crate) fn within_field(self, name: &'static str) -> Self {
+		match self.inner.path.first() {
+			_ if name.is_empty() => self,
+			Some(PathSegment::Name { name: first, .. }) if *first == name => self,
+			_ => self.within_sexpr(name)
+		}
+	}
+
+	/// Record that this error occured inside the element `index` of a collection.
+	pub(crate) fn within_element(mut self, index: usize) -> Self {
+		match self.inner.path.first_mut() {
+			Some(PathSegment::Name { index: idx @ None, .. }) => {
+				*idx = Some(index);
+				self
+			},
+			_ => self.within_index(index)
+		}
+	}
+
+	/// Record that this error occured inside the positional value `index`.
+	pub(crate) fn within_index(mut self, index: usize) -> Self {
+		self.inner.path.insert(0, PathSegment::Index(index));
+		self
+	}
+
 	#[cfg(feature = "backtrace")]
 	pub fn backtrace(&self) -> &backtrace::Backtrace {
-		&*self.backtrace
+		&self.inner.backtrace
 	}
 }
 
@@ -95,6 +342,8 @@ impl Debug for Error {
 		let mut dbg = f.debug_struct("Error");
 		dbg.field("message", &self.kind.to_string());
 		dbg.field("kind", &self.kind);
+		dbg.field("span", &self.inner.span);
+		dbg.field("path", &self.inner.path);
 		#[cfg(feature = "backtrace")]
 		dbg.field("backtrace", self.backtrace());
 		dbg.finish()
@@ -103,25 +352,29 @@ impl Debug for Error {
 
 impl Display for Error {
 	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-		Display::fmt(&self.kind, f)
+		Display::fmt(&self.kind, f)?;
+		if let Some(span) = self.inner.span {
+			write!(f, " at {}", span.start)?;
+		}
+		for (i, segment) in self.inner.path.iter().enumerate() {
+			f.write_str(if i == 0 { " in " } else { " > " })?;
+			Display::fmt(segment, f)?;
+		}
+		Ok(())
 	}
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match &self.kind {
+			ErrorKind::Io(err) => Some(&**err),
+			kind => kind.source()
+		}
+	}
+}
 
 impl de::Error for Error {
 	fn custom<T: Display>(msg: T) -> Self {
-		Error {
-			kind: ErrorKind::Message(msg.to_string()),
-			#[cfg(feature = "backtrace")]
-			backtrace: {
-				let bt = backtrace::Backtrace::new_unresolved();
-				once_cell::sync::Lazy::new(Box::new(move || {
-					let mut bt = bt;
-					bt.resolve();
-					bt
-				}))
-			}
-		}
+		Error::new(ErrorKind::Message(msg.to_string()))
 	}
 }
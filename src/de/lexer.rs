@@ -0,0 +1,314 @@
+//! The lexer of the deserializer. It splits the input into tokens by looking at
+//! each byte only once. All delimiters of the format are ASCII, so a token can
+//! never end in the middle of a multi-byte character.
+
+/// The kind of a token.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum Kind {
+	/// An opening paren, together with the name of the s-expr that follows it.
+	Open,
+
+	/// A closing paren.
+	Close,
+
+	/// A string in quotes. `escaped` is set if it contains any backslash.
+	Quoted { escaped: bool },
+
+	/// An unquoted integer, like `42`, `-1` or `+5`.
+	Int,
+
+	/// An unquoted finite float, like `1.5`, `.5` or `1.0e-3`.
+	Float,
+
+	/// Any other unquoted token.
+	Symbol
+}
+
+/// A token at the start of the input.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Lexeme<'de> {
+	pub(super) kind: Kind,
+
+	/// The complete text of the token, borrowed from the input.
+	pub(super) text: &'de str
+}
+
+impl<'de> Lexeme<'de> {
+	/// The length of the token in bytes.
+	pub(super) fn len(&self) -> usize {
+		self.text.len()
+	}
+
+	/// The name of an s-expr, which might be empty.
+	pub(super) fn name(&self) -> &'de str {
+		debug_assert_eq!(self.kind, Kind::Open);
+		&self.text[1..]
+	}
+
+	/// The content of a quoted string, without the quotes.
+	pub(super) fn content(&self) -> &'de str {
+		debug_assert!(matches!(self.kind, Kind::Quoted { .. }));
+		&self.text[1..self.text.len() - 1]
+	}
+
+	/// The leading identifier of a symbol, if any.
+	pub(super) fn identifier(&self) -> Option<&'de str> {
+		if self.kind != Kind::Symbol {
+			return None;
+		}
+		let len = self.text.bytes().take_while(|b| is_identifier(*b)).count();
+		(len > 0).then(|| &self.text[..len])
+	}
+}
+
+fn is_identifier(b: u8) -> bool {
+	b.is_ascii_alphabetic() || b == b'_'
+}
+
+fn is_name(b: u8) -> bool {
+	b.is_ascii_graphic() && !matches!(b, b'(' | b')' | b'"')
+}
+
+fn is_delimiter(b: u8) -> bool {
+	b.is_ascii_whitespace() || b == b'(' || b == b')'
+}
+
+/// Return the length of the whitespace at the start of the input. This includes
+/// all Unicode whitespace, but tokens only end at ASCII whitespace.
+pub(super) fn whitespace_len(input: &str) -> usize {
+	let len = input.bytes().take_while(u8::is_ascii_whitespace).count();
+	match input.as_bytes().get(len) {
+		// only look at characters if there might be any other whitespace, like a
+		// vertical tab or a non-breaking space
+		Some(b) if *b == 0x0b || !b.is_ascii() => {
+			input.len() - input.trim_start().len()
+		},
+		_ => len
+	}
+}
+
+/// Return the number of leading ASCII digits.
+fn digits(bytes: &[u8]) -> usize {
+	bytes.iter().take_while(|b| b.is_ascii_digit()).count()
+}
+
+/// Classify an unquoted token as a number, or as a symbol if it is none. Numbers
+/// are
+///
+/// ```text
+/// int      = sign? digit+
+/// float    = sign? (digit+ "." digit* | "." digit+) exponent? | sign? digit+ exponent
+/// exponent = ("e" | "E") sign? digit+
+/// sign     = "+" | "-"
+/// ```
+///
+/// A float without a decimal point must not have an unsigned uppercase exponent,
+/// so that hex timestamps like `(tedit 5E000012)` stay symbols. Floats that are
+/// too large to be finite are symbols as well, and so are `inf` and `nan`. Typed
+/// float fields still accept all of these.
+fn number_kind(token: &[u8]) -> Kind {
+	let token = match token.first() {
+		Some(b'+' | b'-') => &token[1..],
+		_ => token
+	};
+
+	let int = digits(token);
+	let mut rest = &token[int..];
+	let mut kind = Kind::Int;
+	let mut scientific = false;
+	if let Some(fraction) = rest.strip_prefix(b".") {
+		let len = digits(fraction);
+		if int + len == 0 {
+			return Kind::Symbol;
+		}
+		rest = &fraction[len..];
+		kind = Kind::Float;
+	} else if int == 0 {
+		return Kind::Symbol;
+	}
+	if let Some(exponent) = rest.strip_prefix(b"e").or(rest.strip_prefix(b"E")) {
+		let exponent = match exponent.first() {
+			Some(b'+' | b'-') => &exponent[1..],
+			// hex timestamps look like floats with an unsigned uppercase exponent
+			_ if kind == Kind::Int && rest[0] == b'E' => {
+				return Kind::Symbol;
+			},
+			_ => exponent
+		};
+		let len = digits(exponent);
+		if len == 0 {
+			return Kind::Symbol;
+		}
+		rest = &exponent[len..];
+		kind = Kind::Float;
+		scientific = true;
+	}
+	if !rest.is_empty() {
+		return Kind::Symbol;
+	}
+	// only an exponent or a lot of digits can overflow
+	if kind == Kind::Float && (scientific || int > 300) {
+		let finite = std::str::from_utf8(token)
+			.ok()
+			.and_then(|token| token.parse::<f64>().ok())
+			.is_some_and(f64::is_finite);
+		if !finite {
+			return Kind::Symbol;
+		}
+	}
+	kind
+}
+
+/// Scan the token at the start of the input. Return `None` if the input is empty
+/// or ends inside of a quoted string.
+pub(super) fn lex(input: &str) -> Option<Lexeme<'_>> {
+	let bytes = input.as_bytes();
+	let (kind, len) = match *bytes.first()? {
+		b'(' => {
+			// the name ends at the next delimiter or quote. Names are ASCII, so it
+			// also ends at whitespace that is not ASCII
+			let name = bytes[1..].iter().take_while(|b| is_name(**b));
+			(Kind::Open, 1 + name.count())
+		},
+		b')' => (Kind::Close, 1),
+		b'"' => {
+			// the string ends at the first quote that is not escaped
+			let mut escaped = false;
+			let mut idx = 1;
+			loop {
+				match *bytes.get(idx)? {
+					b'"' => break,
+					b'\\' => {
+						escaped = true;
+						idx += 2;
+					},
+					_ => idx += 1
+				}
+			}
+			(Kind::Quoted { escaped }, idx + 1)
+		},
+		_ => {
+			let len = bytes
+				.iter()
+				.position(|b| is_delimiter(*b))
+				.unwrap_or(bytes.len());
+			(number_kind(&bytes[..len]), len)
+		}
+	};
+	Some(Lexeme {
+		kind,
+		text: &input[..len]
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn kinds(mut input: &str) -> Vec<(Kind, &str)> {
+		let mut tokens = Vec::new();
+		loop {
+			input = &input[whitespace_len(input)..];
+			match lex(input) {
+				Some(lexeme) => {
+					tokens.push((lexeme.kind, lexeme.text));
+					input = &input[lexeme.len()..];
+				},
+				None => return tokens
+			}
+		}
+	}
+
+	#[test]
+	fn tokens() {
+		assert_eq!(
+			kinds(r#"(at -1.5 2)(layer "F.Cu") (tstamp 1a2b-c) ("x\"y")"#),
+			vec![
+				(Kind::Open, "(at"),
+				(Kind::Float, "-1.5"),
+				(Kind::Int, "2"),
+				(Kind::Close, ")"),
+				(Kind::Open, "(layer"),
+				(Kind::Quoted { escaped: false }, r#""F.Cu""#),
+				(Kind::Close, ")"),
+				(Kind::Open, "(tstamp"),
+				(Kind::Symbol, "1a2b-c"),
+				(Kind::Close, ")"),
+				(Kind::Open, "("),
+				(Kind::Quoted { escaped: true }, r#""x\"y""#),
+				(Kind::Close, ")")
+			]
+		);
+	}
+
+	#[test]
+	fn names_with_digits() {
+		assert_eq!(
+			kinds("(pcbplotparams (psa4output false) (layer_2 F.Cu))"),
+			vec![
+				(Kind::Open, "(pcbplotparams"),
+				(Kind::Open, "(psa4output"),
+				(Kind::Symbol, "false"),
+				(Kind::Close, ")"),
+				(Kind::Open, "(layer_2"),
+				(Kind::Symbol, "F.Cu"),
+				(Kind::Close, ")"),
+				(Kind::Close, ")")
+			]
+		);
+	}
+
+	#[test]
+	fn multi_byte_characters() {
+		assert_eq!(
+			kinds("(name µ \"Ω\")"),
+			vec![
+				(Kind::Open, "(name"),
+				(Kind::Symbol, "µ"),
+				(Kind::Quoted { escaped: false }, "\"Ω\""),
+				(Kind::Close, ")")
+			]
+		);
+	}
+
+	#[test]
+	fn whitespace() {
+		assert_eq!(whitespace_len(" \t\r\n(at"), 4);
+		assert_eq!(whitespace_len("\x0b (at"), 2);
+		assert_eq!(whitespace_len(" \u{a0}\u{2003}(at"), 6);
+		assert_eq!(whitespace_len("µ"), 0);
+		assert_eq!(
+			kinds("(at\u{a0}1\x0b2)"),
+			vec![
+				(Kind::Open, "(at"),
+				(Kind::Symbol, "1\x0b2"),
+				(Kind::Close, ")")
+			]
+		);
+	}
+
+	#[test]
+	fn numbers() {
+		for int in ["0", "42", "-1", "+5"] {
+			assert_eq!(number_kind(int.as_bytes()), Kind::Int, "{int}");
+		}
+		for float in [
+			"1.5", "-1.", ".5", "+.5", "1.e-3", "2.5E+10", "1.0e-400", "1e-3", "1e5",
+			"-2E-3", "3E+2"
+		] {
+			assert_eq!(number_kind(float.as_bytes()), Kind::Float, "{float}");
+		}
+		for symbol in [
+			"-", "+", ".", "e5", "1e", "1e-", "1.2.3", "5D9F72AF", "F.Cu", "inf",
+			"-Infinity", "NaN", "5E000012", "5E241234", "-1.0e309", "1e999"
+		] {
+			assert_eq!(number_kind(symbol.as_bytes()), Kind::Symbol, "{symbol}");
+		}
+	}
+
+	#[test]
+	fn unterminated_string() {
+		assert!(lex(r#""abc\""#).is_none());
+		assert!(lex("").is_none());
+	}
+}
@@ -1,32 +1,97 @@
 use paste::paste;
 use serde::{
 	de::{
-		self, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess,
-		Visitor
+		self,
+		value::{MapDeserializer, SeqDeserializer},
+		DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
+		SeqAccess, VariantAccess, Visitor
 	},
 	forward_to_deserialize_any, Deserialize
 };
-use std::{borrow::Cow, fmt::Display, str::FromStr};
+use crate::{
+	extra::{EXTRA, EXTRA_FIELD},
+	private::{
+		split_tagged, TAGGED, UNTAGGED_FLOAT, UNTAGGED_INT, UNTAGGED_STRING
+	},
+	value::VALUE
+};
+use lexer::{lex, whitespace_len, Kind, Lexeme};
+use std::{
+	borrow::Cow,
+	cell::Cell,
+	fmt::Display,
+	io,
+	str::FromStr,
+	string::FromUtf8Error,
+	sync::Arc
+};
 
 mod error;
-pub use error::{Error, ErrorKind};
+mod lexer;
+pub use error::{Error, ErrorKind, PathSegment, Position, Span};
+
+/// Return the name of the children that a field collects, if the field's name ends
+/// with a `*`.
+pub(crate) fn collection_name(field: &str) -> Option<&str> {
+	field.strip_suffix('*').filter(|name| !name.is_empty())
+}
+
+/// Remove the escapes from the content of a quoted string, the same way KiCad
+/// does. Unknown escapes are kept as they are. Hex (`\x41`) and octal (`\101`)
+/// escapes produce single bytes, which have to form valid UTF-8 together with the
+/// rest of the string.
+pub(crate) fn unescape(v: &str) -> Result<String, FromUtf8Error> {
+	let bytes = v.as_bytes();
+	let mut value = Vec::with_capacity(bytes.len());
+	let mut idx = 0;
+	while idx < bytes.len() {
+		let rest = &bytes[idx + 1..];
+		let (byte, len) = match (bytes[idx], rest.first()) {
+			(b'\\', Some(b'x')) => match parse_digits(&rest[1..], 2, 16) {
+				Some((byte, len)) => (byte, len + 2),
+				None => (b'\\', 1)
+			},
+			(b'\\', Some(b'0'..=b'7')) => match parse_digits(rest, 3, 8) {
+				Some((byte, len)) => (byte, len + 1),
+				None => (b'\\', 1)
+			},
+			(b'\\', Some(&escaped)) => match escaped {
+				b'"' | b'\\' => (escaped, 2),
+				b'a' => (0x07, 2),
+				b'b' => (0x08, 2),
+				b'f' => (0x0c, 2),
+				b'n' => (b'\n', 2),
+				b'r' => (b'\r', 2),
+				b't' => (b'\t', 2),
+				b'v' => (0x0b, 2),
+				_ => (b'\\', 1)
+			},
+			(byte, _) => (byte, 1)
+		};
+		value.push(byte);
+		idx += len;
+	}
+	String::from_utf8(value)
+}
+
+/// Parse up to `max` digits in the given radix at the start of `bytes`, and return
+/// their value truncated to a byte and the number of digits.
+fn parse_digits(bytes: &[u8], max: usize, radix: u32) -> Option<(u8, usize)> {
+	let digits: Vec<_> = bytes
+		.iter()
+		.take(max)
+		.map_while(|byte| char::from(*byte).to_digit(radix))
+		.collect();
+	let value = digits.iter().fold(0, |value, digit| value * radix + digit);
+	match digits.len() {
+		0 => None,
+		len => Some((value as u8, len))
+	}
+}
 
 macro_rules! error {
 	($kind:ident $(($($arg:expr),+))?) => {
-		{
-			Error {
-				kind: ErrorKind::$kind $(($($arg),+))?,
-				#[cfg(feature = "backtrace")]
-				backtrace: {
-					let bt = backtrace::Backtrace::new_unresolved();
-					once_cell::sync::Lazy::new(Box::new(move || {
-						let mut bt = bt;
-						bt.resolve();
-						bt
-					}))
-				}
-			}
-		}
+		Error::new(ErrorKind::$kind $(($($arg),+))?)
 	};
 }
 
@@ -37,17 +102,204 @@ macro_rules! bail {
 }
 
 pub struct Deserializer<'de> {
-	input: &'de str
+	/// The complete input, used to locate errors.
+	original: &'de str,
+
+	/// The remaining input.
+	input: &'de str,
+
+	/// Set to true to match the children of all structs to their fields by name.
+	unordered: bool,
+
+	/// The names of the structs whose children are matched to their fields by name.
+	unordered_structs: Vec<&'static str>,
+
+	/// Set to true to skip s-exprs that are not known to the struct they appear in.
+	lenient: bool,
+
+	/// Set to true to reject floats that are NaN or infinite.
+	strict_floats: bool,
+
+	/// The unknown s-exprs of every struct with an extra field that is currently
+	/// being deserialized.
+	extra: Vec<Vec<&'de str>>,
+
+	/// The last token that was peeked at, if any.
+	lookahead: Cell<Option<Lexeme<'de>>>
 }
 
 impl<'de> Deserializer<'de> {
+	#[allow(clippy::should_implement_trait)]
 	pub fn from_str(input: &'de str) -> Self {
-		Self { input }
+		Self {
+			original: input,
+			input,
+			unordered: false,
+			unordered_structs: Vec::new(),
+			lenient: false,
+			strict_floats: false,
+			extra: Vec::new(),
+			lookahead: Cell::new(None)
+		}
+	}
+
+	/// Create a deserializer from a byte buffer. This fails if the input is not
+	/// valid UTF-8, with the error pointing to the first invalid byte.
+	pub fn from_slice(input: &'de [u8]) -> Result<Self> {
+		match std::str::from_utf8(input) {
+			Ok(input) => Ok(Self::from_str(input)),
+			Err(err) => {
+				let mut error = error!(InvalidUtf8);
+				error.inner.span = Some(Span::invalid_utf8(input, &err));
+				Err(error)
+			}
+		}
+	}
+
+	/// Match the children of every struct to its fields by their name, regardless
+	/// of their order in the input. Values without a name, like numbers, are still
+	/// matched to the fields that have no named child in the order they appear.
+	///
+	/// ```rust
+	/// # use serde::Deserialize;
+	/// use serde_kicad_sexpr::de::Deserializer;
+	///
+	/// # #[derive(Debug, PartialEq)]
+	/// #[derive(Deserialize)]
+	/// #[serde(rename = "size")]
+	/// struct Size(f32, f32);
+	///
+	/// # #[derive(Debug, PartialEq)]
+	/// #[derive(Deserialize)]
+	/// #[serde(rename = "pad")]
+	/// struct Pad {
+	///     index: String,
+	///     size: Size,
+	///     layers: Vec<String>
+	/// }
+	///
+	/// let input = r#"(pad (layers "F.Cu") "1" (size 1 1))"#;
+	/// let mut de = Deserializer::from_str(input).unordered(true);
+	/// let pad = Pad::deserialize(&mut de).unwrap();
+	/// # assert_eq!(pad, Pad {
+	/// #     index: "1".to_owned(),
+	/// #     size: Size(1.0, 1.0),
+	/// #     layers: vec!["F.Cu".to_owned()]
+	/// # });
+	/// ```
+	pub fn unordered(mut self, unordered: bool) -> Self {
+		self.unordered = unordered;
+		self
+	}
+
+	/// Match the children of the structs with these names to their fields by their
+	/// name, like [`unordered`](Self::unordered) does for all structs.
+	pub fn unordered_structs<I>(mut self, names: I) -> Self
+	where
+		I: IntoIterator<Item = &'static str>
+	{
+		self.unordered_structs.extend(names);
+		self
+	}
+
+	/// Skip child s-exprs that are unknown to the struct they appear in, instead of
+	/// failing. This allows reading files that were written by newer versions of
+	/// KiCad, which can contain s-exprs your types don't know about yet.
+	///
+	/// An s-expr is unknown if it is not named like any field of its struct. Inside
+	/// a struct with a field with an empty name, other s-exprs could belong to that
+	/// collection, so they are only skipped in front of a value of a known name, or
+	/// if they are not named like the elements of the collection. In any case, an
+	/// s-expr has to be named like the field it belongs to in order to not be
+	/// skipped.
+	///
+	/// ```rust
+	/// # use serde::Deserialize;
+	/// use serde_kicad_sexpr::de::Deserializer;
+	///
+	/// # #[derive(Debug, PartialEq)]
+	/// #[derive(Deserialize)]
+	/// #[serde(rename = "size")]
+	/// struct Size(f32, f32);
+	///
+	/// # #[derive(Debug, PartialEq)]
+	/// #[derive(Deserialize)]
+	/// #[serde(rename = "font")]
+	/// struct Font {
+	///     size: Size,
+	///     bold: bool
+	/// }
+	///
+	/// let input = "(font (size 1 1) (face \"KiCad Font\") bold)";
+	/// let mut de = Deserializer::from_str(input).lenient(true);
+	/// let font = Font::deserialize(&mut de).unwrap();
+	/// # assert_eq!(font, Font { size: Size(1.0, 1.0), bold: true });
+	/// ```
+	pub fn lenient(mut self, lenient: bool) -> Self {
+		self.lenient = lenient;
+		self
+	}
+
+	/// Reject floats that are NaN or infinite, including numbers that are too
+	/// large for their type, instead of deserializing them.
+	///
+	/// ```rust
+	/// # use serde::Deserialize;
+	/// use serde_kicad_sexpr::de::{Deserializer, ErrorKind};
+	///
+	/// #[derive(Deserialize)]
+	/// #[serde(rename = "width")]
+	/// struct Width(f32);
+	///
+	/// let mut de = Deserializer::from_str("(width 1e40)").strict_floats(true);
+	/// let err = Width::deserialize(&mut de).err().unwrap();
+	/// assert_eq!(err.kind, ErrorKind::NonFiniteFloat);
+	/// ```
+	pub fn strict_floats(mut self, strict_floats: bool) -> Self {
+		self.strict_floats = strict_floats;
+		self
+	}
+
+	fn is_unordered(&self, name: &str) -> bool {
+		self.unordered || self.unordered_structs.contains(&name)
+	}
+
+	/// Return the current position in the input.
+	pub fn position(&self) -> Position {
+		Position::locate(self.original, self.offset())
+	}
+
+	fn offset(&self) -> usize {
+		self.original.len() - self.input.len()
+	}
+
+	/// Attach the span of the current token to an error that doesn't know its
+	/// location yet.
+	fn locate_err(&self, mut err: Error) -> Error {
+		if err.inner.span.is_none() {
+			err.inner.span = Some(Span::locate(
+				self.original,
+				self.offset(),
+				self.token_len()
+			));
+		}
+		err
+	}
+
+	/// Run `f` and attach the current location to any error it returns.
+	fn located<T, F>(&mut self, f: F) -> Result<T>
+	where
+		F: FnOnce(&mut Self) -> Result<T>
+	{
+		f(self).map_err(|err| self.locate_err(err))
 	}
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Deserialize a value from a string. Strings without escapes are borrowed from the
+/// input, so `&str` and `Cow<str>` fields can avoid an allocation. Escaped strings
+/// can only be deserialized into owned strings or a `Cow<str>`.
 pub fn from_str<'de, T>(input: &'de str) -> Result<T>
 where
 	T: Deserialize<'de>
@@ -57,6 +309,31 @@ where
 	Ok(value)
 }
 
+/// Deserialize a value from a byte buffer. The input has to be valid UTF-8,
+/// otherwise the error points to the first invalid byte.
+pub fn from_slice<'de, T>(input: &'de [u8]) -> Result<T>
+where
+	T: Deserialize<'de>
+{
+	let mut deserializer = Deserializer::from_slice(input)?;
+	let value = T::deserialize(&mut deserializer)?;
+	Ok(value)
+}
+
+/// Deserialize a value from a reader. The input is read into memory completely
+/// before it gets deserialized.
+pub fn from_reader<R, T>(mut reader: R) -> Result<T>
+where
+	R: io::Read,
+	T: DeserializeOwned
+{
+	let mut buf = Vec::new();
+	reader
+		.read_to_end(&mut buf)
+		.map_err(|err| error!(Io(Arc::new(err))))?;
+	from_slice(&buf)
+}
+
 enum Token {
 	String,
 	Int,
@@ -74,73 +351,155 @@ impl<'de> Deserializer<'de> {
 	}
 
 	fn skip_whitespace(&mut self) {
-		self.input = self.input.trim_start();
+		self.input = &self.input[whitespace_len(self.input)..];
 	}
 
 	fn peek_char(&self) -> Result<char> {
 		self.input.chars().next().ok_or_else(|| error!(Eof))
 	}
 
-	fn next_char(&mut self) -> Result<char> {
-		let ch = self.peek_char()?;
-		self.input = &self.input[ch.len_utf8()..];
-		Ok(ch)
+	/// Return the token at the start of the input. The last token is remembered, so
+	/// that peeking at a token and then parsing it only scans it once.
+	fn peek(&self) -> Result<Lexeme<'de>> {
+		if let Some(lexeme) = self.lookahead.get() {
+			if lexeme.text.as_ptr() == self.input.as_ptr() {
+				return Ok(lexeme);
+			}
+		}
+		let lexeme = lex(self.input).ok_or_else(|| error!(Eof))?;
+		self.lookahead.set(Some(lexeme));
+		Ok(lexeme)
 	}
 
-	fn peek_token(&self) -> Result<Token> {
-		let mut chars = self.input.chars().peekable();
-		if chars.peek().is_none() {
-			bail!(Eof);
-		}
+	/// Return the length of the token at the start of the input, for error spans.
+	fn token_len(&self) -> usize {
+		self.peek()
+			.map(|lexeme| lexeme.len())
+			.unwrap_or(self.input.len())
+	}
 
-		let mut int = true;
-		while let Some(ch) = chars.next() {
-			match ch {
-				'(' => return Ok(Token::SExpr),
-				'.' => {
-					int = false;
-				},
-				'-' => {},
-				ch if ch.is_ascii_whitespace() => break,
-				ch if ch.is_ascii_digit() => {},
-				_ => return Ok(Token::String)
-			};
-		}
+	fn peek_token(&self) -> Result<Token> {
+		Ok(match self.peek()?.kind {
+			Kind::Open => Token::SExpr,
+			// a missing value is reported as a missing number
+			Kind::Int | Kind::Close => Token::Int,
+			Kind::Float => Token::Float,
+			Kind::Quoted { .. } | Kind::Symbol => Token::String
+		})
+	}
 
-		Ok(match int {
-			true => Token::Int,
-			false => Token::Float
+	/// Return true if the next token is an integer that fits into neither an `i64`
+	/// nor a `u64`.
+	fn peek_large_int(&self) -> bool {
+		self.peek().is_ok_and(|lexeme| {
+			lexeme.kind == Kind::Int
+				&& lexeme.text.parse::<i64>().is_err()
+				&& lexeme.text.parse::<u64>().is_err()
 		})
 	}
 
 	fn peek_identifier(&self) -> Option<&'de str> {
-		let len: usize = self
-			.input
-			.chars()
-			.take_while(|ch| ch.is_ascii_alphabetic() || *ch == '_')
-			.map(|ch| ch.len_utf8())
-			.sum();
-		if len == 0 {
-			return None;
-		}
-		Some(&self.input[..len])
+		self.peek().ok()?.identifier()
 	}
 
 	fn peek_sexpr_identifier(&self) -> Result<&'de str> {
-		let mut chars = self.input.chars();
-		let next = chars.next().ok_or_else(|| error!(Eof))?;
+		let next = self.peek_char()?;
 		if next != '(' {
 			bail!(ExpectedSExpr(next));
 		}
-		let paren = '('.len_utf8();
-		let len: usize = chars
-			.take_while(|ch| ch.is_ascii_alphabetic() || *ch == '_')
-			.map(|ch| ch.len_utf8())
-			.sum();
-		if len == 0 {
+		let name = self.peek()?.name();
+		if name.is_empty() {
 			bail!(ExpectedIdentifier);
 		}
-		Ok(&self.input[paren..paren + len])
+		Ok(name)
+	}
+
+	/// Skip the next token or s-expr, including everything inside of it.
+	fn skip_value(&mut self) -> Result<()> {
+		let mut depth = 0_usize;
+		loop {
+			self.skip_whitespace();
+			let lexeme = self.peek()?;
+			match lexeme.kind {
+				Kind::Open => depth += 1,
+				Kind::Close if depth > 0 => depth -= 1,
+				Kind::Close => {
+					bail!(ExpectedEoe);
+				},
+				_ => {}
+			}
+			self.input = &self.input[lexeme.len()..];
+			if depth == 0 {
+				return Ok(());
+			}
+		}
+	}
+
+	/// Skip all s-exprs at the start of the input whose name is not `known`, if this
+	/// deserializer is lenient or if they should be captured as extra children of
+	/// the current struct. Return true if any s-expr was skipped.
+	fn skip_unknown<F>(&mut self, known: F, capture: bool) -> Result<bool>
+	where
+		F: Fn(&str) -> bool
+	{
+		let mut skipped = false;
+		while self.lenient || capture {
+			self.skip_whitespace();
+			if self.peek_char()? != '(' || known(self.peek_sexpr_identifier()?) {
+				break;
+			}
+			if capture {
+				if let Some(extra) = self.extra.last_mut() {
+					extra.push(self.input);
+				}
+			}
+			self.skip_value()?;
+			skipped = true;
+		}
+		Ok(skipped)
+	}
+
+	/// Find the value that selects the variant of a tagged enum, without consuming
+	/// any input. Every segment of the path either enters the child s-expr with
+	/// that name, or skips that many values of the current s-expr. The tag is the
+	/// value after the last segment.
+	fn peek_tag(&mut self, path: &'static str) -> Result<Cow<'de, str>> {
+		let start = self.input;
+		let tag = self.find_tag(path);
+		self.input = start;
+		tag
+	}
+
+	fn find_tag(&mut self, path: &'static str) -> Result<Cow<'de, str>> {
+		self.skip_whitespace();
+		let head = self.peek_sexpr_identifier()?;
+		self.consume('('.len_utf8() + head.len())?;
+		for segment in path.split(',').map(str::trim) {
+			let skip = segment.parse::<usize>().ok();
+			let mut index = 0;
+			loop {
+				self.skip_whitespace();
+				match self.peek_char()? {
+					')' => {
+						bail!(MissingTag(path));
+					},
+					_ if skip == Some(index) => break,
+					'(' if skip.is_none()
+						&& self.peek_sexpr_identifier()? == segment =>
+					{
+						self.consume('('.len_utf8() + segment.len())?;
+						break;
+					},
+					_ => self.skip_value()?
+				}
+				index += 1;
+			}
+		}
+		self.skip_whitespace();
+		match self.peek_char()? {
+			'(' | ')' => Err(error!(MissingTag(path))),
+			_ => self.parse_string()
+		}
 	}
 
 	fn consume(&mut self, len: usize) -> Result<()> {
@@ -156,88 +515,72 @@ impl<'de> Deserializer<'de> {
 		T: FromStr,
 		T::Err: Display
 	{
-		let len = self
-			.input
-			.chars()
-			.take_while(|ch| !ch.is_ascii_whitespace() && *ch != ')')
-			.map(|ch| ch.len_utf8())
-			.sum();
-		if len == 0 {
-			bail!(ExpectedNumber);
-		}
-		let number = &self.input[..len];
-		let number = number
+		let number = match self.peek() {
+			Ok(lexeme) if lexeme.kind != Kind::Close => lexeme.text,
+			_ => {
+				bail!(ExpectedNumber);
+			}
+		};
+		let value = number
 			.parse()
 			.map_err(|err: T::Err| error!(Message(err.to_string())))?;
-		self.input = &self.input[len..];
-		Ok(number)
+		self.input = &self.input[number.len()..];
+		Ok(value)
 	}
 
-	fn parse_string(&mut self) -> Result<Cow<'de, str>> {
-		match self.peek_char()? {
-			'(' => Err(error!(ExpectedString)),
-
-			'"' => {
-				self.consume('"'.len_utf8())?;
-				let mut value = String::new();
-				loop {
-					let len: usize = self
-						.input
-						.chars()
-						.take_while(|ch| *ch != '"')
-						.map(|ch| ch.len_utf8())
-						.sum();
-					if len >= self.input.len() {
-						bail!(Eof);
-					}
-
-					let mut start_idx = value.chars().count();
-					value += &self.input[..len + 1];
-					self.input = &self.input[len + 1..];
-					while let Some(idx) = (&value[start_idx..]).find(r"\\") {
-						let idx = start_idx + idx;
-						value.replace_range(idx..idx + 2, r"\");
-						start_idx = idx + 1;
-					}
+	/// Parse a float, and reject it if it is not finite and only finite floats are
+	/// allowed.
+	fn parse_float<T>(&mut self, is_finite: fn(T) -> bool) -> Result<T>
+	where
+		T: Copy + FromStr,
+		T::Err: Display
+	{
+		let input = self.input;
+		let value = self.parse_number()?;
+		if self.strict_floats && !is_finite(value) {
+			self.input = input;
+			bail!(NonFiniteFloat);
+		}
+		Ok(value)
+	}
 
-					if value.ends_with(r#"\""#) && start_idx < value.len() - 1 {
-						value.remove(value.len() - 2);
-					} else if value.ends_with(r#"""#) {
-						value.remove(value.len() - 1);
-						break;
-					} else {
-						unreachable!();
-					}
-				}
-				Ok(value.into())
+	fn parse_string(&mut self) -> Result<Cow<'de, str>> {
+		let lexeme = self.peek()?;
+		let value = match lexeme.kind {
+			Kind::Open => {
+				bail!(ExpectedString);
 			},
-
-			_ => {
-				let len = self
-					.input
-					.chars()
-					.take_while(|ch| !ch.is_ascii_whitespace() && *ch != ')')
-					.map(|ch| ch.len_utf8())
-					.sum();
-				if len == 0 {
-					bail!(Eof);
-				}
-				let value = &self.input[..len];
-				self.input = &self.input[len..];
-				Ok(value.into())
-			}
-		}
+			Kind::Close => {
+				bail!(Eof);
+			},
+			// strings without escapes are borrowed from the input
+			Kind::Quoted { escaped: false } => Cow::Borrowed(lexeme.content()),
+			Kind::Quoted { escaped: true } => Cow::Owned(
+				unescape(lexeme.content()).map_err(|_| error!(InvalidUtf8))?
+			),
+			Kind::Int | Kind::Float | Kind::Symbol => Cow::Borrowed(lexeme.text)
+		};
+		self.input = &self.input[lexeme.len()..];
+		Ok(value)
 	}
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
 	type Error = Error;
 
-	fn deserialize_any<V>(self, _: V) -> Result<V::Value>
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
 	where
 		V: Visitor<'de>
 	{
-		Err(error!(ExpectedStruct))
+		self.located(|de| {
+			de.skip_whitespace();
+			if de.peek_char()? != '(' {
+				bail!(ExpectedStruct);
+			}
+			let v = visitor.visit_seq(SExprTuple::untyped(de)?)?;
+			de.check_no_trailing_tokens()?;
+			Ok(v)
+		})
 	}
 
 	fn deserialize_struct<V>(
@@ -249,9 +592,13 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 	where
 		V: Visitor<'de>
 	{
-		let v = visitor.visit_map(SExpr::new(self, name, fields)?)?;
-		self.check_no_trailing_tokens()?;
-		Ok(v)
+		self.located(|de| {
+			let v = visitor
+				.visit_map(SExpr::new(de, name, fields)?)
+				.map_err(|err| err.within_sexpr(name))?;
+			de.check_no_trailing_tokens()?;
+			Ok(v)
+		})
 	}
 
 	fn deserialize_unit_struct<V>(
@@ -262,12 +609,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 	where
 		V: Visitor<'de>
 	{
-		SExpr::consume_beginning(self, name)?;
-		if self.next_char()? != ')' {
-			bail!(ExpectedEoe);
-		}
-		self.check_no_trailing_tokens()?;
-		visitor.visit_unit()
+		self.located(|de| {
+			SExpr::consume_unit(de, name)?;
+			de.check_no_trailing_tokens()?;
+			visitor.visit_unit()
+		})
 	}
 
 	fn deserialize_newtype_struct<V>(
@@ -278,9 +624,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 	where
 		V: Visitor<'de>
 	{
-		let v = visitor.visit_seq(SExprTuple::new(self, name)?)?;
-		self.check_no_trailing_tokens()?;
-		Ok(v)
+		if name == VALUE {
+			return visitor.visit_newtype_struct(self);
+		}
+		self.deserialize_tuple_struct(name, 1, visitor)
 	}
 
 	fn deserialize_tuple_struct<V>(
@@ -292,40 +639,69 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 	where
 		V: Visitor<'de>
 	{
-		let v = visitor.visit_seq(SExprTuple::new(self, name)?)?;
-		self.check_no_trailing_tokens()?;
-		Ok(v)
+		self.located(|de| {
+			let v = visitor
+				.visit_seq(SExprTuple::new(de, name)?)
+				.map_err(|err| err.within_sexpr(name))?;
+			de.check_no_trailing_tokens()?;
+			Ok(v)
+		})
 	}
 
 	fn deserialize_enum<V>(
 		self,
-		_name: &'static str,
-		_variants: &'static [&'static str],
+		name: &'static str,
+		variants: &'static [&'static str],
 		visitor: V
 	) -> Result<V::Value>
 	where
 		V: Visitor<'de>
 	{
-		let v = visitor.visit_enum(Enum::new(self))?;
-		self.check_no_trailing_tokens()?;
-		Ok(v)
+		if name == TAGGED {
+			let (path, _, _) = split_tagged(variants);
+			return self.located(|de| {
+				let tag = de.peek_tag(path)?;
+				visitor.visit_enum(TaggedEnum { tag, content: de })
+			});
+		}
+		self.located(|de| {
+			let v = visitor.visit_enum(Enum::new(de, variants))?;
+			de.check_no_trailing_tokens()?;
+			Ok(v)
+		})
+	}
+
+	fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.located(|de| {
+			de.skip_value()?;
+			de.check_no_trailing_tokens()?;
+			visitor.visit_unit()
+		})
 	}
 
 	forward_to_deserialize_any! {
 		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-		bytes byte_buf option unit seq tuple map identifier ignored_any
+		bytes byte_buf option unit seq tuple map identifier
 	}
 }
 
-/// Deserialize an enum with only newtype variants whose variant names match the
-/// names of the contained s-exprs.
+/// Deserialize an enum whose variants are s-exprs. Newtype variants must match the
+/// names of the contained s-exprs, and tuple and struct variants are s-exprs named
+/// like the variant.
 struct Enum<'a, 'de> {
-	de: &'a mut Deserializer<'de>
+	de: &'a mut Deserializer<'de>,
+	variants: &'static [&'static str]
 }
 
 impl<'a, 'de> Enum<'a, 'de> {
-	fn new(de: &'a mut Deserializer<'de>) -> Self {
-		Self { de }
+	fn new(
+		de: &'a mut Deserializer<'de>,
+		variants: &'static [&'static str]
+	) -> Self {
+		Self { de, variants }
 	}
 }
 
@@ -348,7 +724,7 @@ impl<'a, 'de> VariantAccess<'de> for Enum<'a, 'de> {
 	type Error = Error;
 
 	fn unit_variant(self) -> Result<(), Self::Error> {
-		Err(error!(NonNewtypeEnumVariant))
+		Err(error!(UnitVariantInSExpr))
 	}
 
 	fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
@@ -360,24 +736,32 @@ impl<'a, 'de> VariantAccess<'de> for Enum<'a, 'de> {
 
 	fn tuple_variant<V>(
 		self,
-		_len: usize,
-		_visitor: V
+		len: usize,
+		visitor: V
 	) -> Result<V::Value, Self::Error>
 	where
 		V: Visitor<'de>
 	{
-		Err(error!(NonNewtypeEnumVariant))
+		SExprVariant {
+			de: self.de,
+			variants: self.variants
+		}
+		.tuple_variant(len, visitor)
 	}
 
 	fn struct_variant<V>(
 		self,
-		_fields: &'static [&'static str],
-		_visitor: V
+		fields: &'static [&'static str],
+		visitor: V
 	) -> Result<V::Value, Self::Error>
 	where
 		V: Visitor<'de>
 	{
-		Err(error!(NonNewtypeEnumVariant))
+		SExprVariant {
+			de: self.de,
+			variants: self.variants
+		}
+		.struct_variant(fields, visitor)
 	}
 }
 
@@ -386,7 +770,20 @@ struct SExpr<'a, 'de> {
 	de: &'a mut Deserializer<'de>,
 	fields: &'static [&'static str],
 	index: usize,
-	skip_to: Option<usize>
+	skip_to: Option<usize>,
+
+	/// The children of the s-expr if they are matched to the fields by name.
+	children: Option<Children<'de>>,
+
+	/// The index of the field that keeps the unknown children, until it was
+	/// returned.
+	extra: Option<usize>,
+
+	/// Set to true if unknown children are kept instead of failing.
+	capture: bool,
+
+	/// The input starting after the name of the s-expr.
+	start: &'de str
 }
 
 impl<'a, 'de> SExpr<'a, 'de> {
@@ -403,21 +800,69 @@ impl<'a, 'de> SExpr<'a, 'de> {
 		Ok(())
 	}
 
+	/// Consume the closing parenthesis of an s-expr, which must not have any
+	/// content left.
+	fn consume_end(de: &mut Deserializer<'de>) -> Result<()> {
+		de.skip_whitespace();
+		if de.peek_char()? != ')' {
+			bail!(ExpectedEoe);
+		}
+		de.consume(')'.len_utf8())
+	}
+
+	/// Consume a unit s-expr, i.e. an s-expr with a name but no content.
+	fn consume_unit(de: &mut Deserializer<'de>, name: &'static str) -> Result<()> {
+		Self::consume_beginning(de, name)?;
+		Self::consume_end(de)
+	}
+
 	fn new(
 		de: &'a mut Deserializer<'de>,
 		name: &'static str,
 		fields: &'static [&'static str]
 	) -> Result<Self> {
 		Self::consume_beginning(de, name)?;
+		Self::body(de, name, fields)
+	}
+
+	/// Deserialize the content of an s-expr whose beginning was already consumed.
+	fn body(
+		de: &'a mut Deserializer<'de>,
+		name: &'static str,
+		fields: &'static [&'static str]
+	) -> Result<Self> {
+		// collections can be interleaved with other children, so they can only be
+		// found by name
+		let collects = fields.iter().any(|field| collection_name(field).is_some());
+		let children = match de.is_unordered(name) || collects {
+			true => Some(Children::scan(de)?),
+			false => None
+		};
+		let extra = fields.iter().position(|field| *field == EXTRA_FIELD);
+		let capture = extra.is_some() && children.is_none();
+		if capture {
+			de.extra.push(Vec::new());
+		}
 		Ok(Self {
+			start: de.input,
 			de,
 			fields,
 			index: 0,
-			skip_to: None
+			skip_to: None,
+			children,
+			extra,
+			capture
 		})
 	}
 
 	fn check_eoe(&mut self) -> Result<()> {
+		// without a collection, all s-exprs not named like a field are unknown
+		let fields = self.fields;
+		if self.skip_to.is_none() && !fields.contains(&"") {
+			self.de
+				.skip_unknown(|name| fields.contains(&name), self.capture)?;
+		}
+
 		self.de.skip_whitespace();
 		if self.skip_to.is_none() && self.de.peek_char()? == ')' {
 			self.de.consume(1)?;
@@ -436,6 +881,22 @@ impl<'a, 'de> SExpr<'a, 'de> {
 			panic!("There was no key and there is no value");
 		}
 
+		if self.fields[self.index] == EXTRA_FIELD {
+			let (items, collected) = match &mut self.children {
+				Some(children) => children.take_extra(self.fields),
+				None => (self.captured()?, Vec::new())
+			};
+			return seed.deserialize(Captured {
+				de: self.de,
+				items: items.into_iter(),
+				collected: collected.into_iter()
+			});
+		}
+
+		if let Some(children) = &mut self.children {
+			return children.next_value_seed(self.de, self.fields, self.index, seed);
+		}
+
 		// booleans are represented in this weird way where they are simply missing if they are
 		// false. This means that if we detect a boolean ahead of our current index, we'll assume
 		// everything inbetween is either None or false, and skip ahead.
@@ -460,7 +921,33 @@ impl<'a, 'de> SExpr<'a, 'de> {
 			}
 		}
 
-		seed.deserialize(Field::new(self.de, Some(self.fields[self.index])))
+		let field = Field::new(self.de, Some(self.fields[self.index]))
+			.followed_by(&self.fields[self.index + 1..])
+			.skipping_unknown(Some(self.fields), self.capture);
+		seed.deserialize(field)
+	}
+
+	/// Return the unknown children that were captured, and their positions among
+	/// all children of the s-expr.
+	fn captured(&mut self) -> Result<Vec<(usize, &'de str)>> {
+		let items = self.de.extra.pop().unwrap_or_default();
+		let input = self.de.input;
+		self.de.input = self.start;
+		let mut positions = Vec::with_capacity(items.len());
+		let mut index = 0;
+		for item in items {
+			loop {
+				self.de.skip_whitespace();
+				if self.de.input.len() <= item.len() {
+					break;
+				}
+				self.de.skip_value()?;
+				index += 1;
+			}
+			positions.push((index, item));
+		}
+		self.de.input = input;
+		Ok(positions)
 	}
 }
 
@@ -471,16 +958,44 @@ impl<'a, 'de> MapAccess<'de> for SExpr<'a, 'de> {
 	where
 		K: DeserializeSeed<'de>
 	{
+		// the field that keeps the unknown children comes last, after all other
+		// children were read
+		if let Some(children) = &self.children {
+			if self.extra == Some(self.index) {
+				self.index += 1;
+			}
+			if self.index >= self.fields.len() {
+				match self.extra.take() {
+					Some(extra) => self.index = extra,
+					None => {
+						children.finish(self.de)?;
+						return Ok(None);
+					}
+				}
+			}
+			return seed.deserialize(FieldIdent(self.fields[self.index])).map(Some);
+		}
+
 		self.check_eoe()?;
 
 		loop {
+			if self.extra == Some(self.index) {
+				self.index += 1;
+				continue;
+			}
 			if self.index >= self.fields.len() {
-				return Ok(None);
+				match self.extra.take() {
+					Some(extra) => {
+						self.index = extra;
+						break;
+					},
+					None => return Ok(None)
+				}
 			}
 
 			// special case: if the ident is empty ("") and we are set to skip it, don't even
 			// return the field.
-			if self.fields[self.index] == "" {
+			if self.fields[self.index].is_empty() {
 				if let Some(skip_to) = self.skip_to {
 					if skip_to == self.index {
 						self.skip_to = None;
@@ -501,64 +1016,588 @@ impl<'a, 'de> MapAccess<'de> for SExpr<'a, 'de> {
 	where
 		T: DeserializeSeed<'de>
 	{
-		let value = self.next_value_seed_impl(seed)?;
-		self.index += 1;
-		self.check_eoe()?;
+		let field = self.fields[self.index];
+		let value = self
+			.next_value_seed_impl(seed)
+			.map_err(|err| err.within_field(field))?;
+		self.index = match field {
+			EXTRA_FIELD => self.fields.len(),
+			_ => self.index + 1
+		};
+		if self.children.is_none() {
+			self.check_eoe()?;
+		}
 		Ok(value)
 	}
 }
 
-/// Deserialize an s-expr in tuple format. It cannot contain booleans.
-struct SExprTuple<'a, 'de> {
-	de: &'a mut Deserializer<'de>,
-	end: bool
+/// The children of an s-expr whose fields are matched by name instead of by their
+/// order in the input.
+struct Children<'de> {
+	items: Vec<Child<'de>>,
+
+	/// The input starting at the closing parenthesis of the s-expr.
+	close: &'de str
 }
 
-impl<'a, 'de> SExprTuple<'a, 'de> {
-	fn new(de: &'a mut Deserializer<'de>, name: &'static str) -> Result<Self> {
-		SExpr::consume_beginning(de, name)?;
-		Ok(Self { de, end: false })
-	}
+struct Child<'de> {
+	/// The input starting at this child.
+	input: &'de str,
+	kind: ChildKind<'de>,
+	used: bool
+}
 
-	fn check_eoe(&mut self) -> Result<()> {
-		if self.end {
-			return Ok(());
-		}
+enum ChildKind<'de> {
+	/// An s-expr with this name.
+	SExpr(&'de str),
 
-		self.de.skip_whitespace();
-		if self.de.peek_char()? == ')' {
-			self.de.consume(')'.len_utf8())?;
-			self.end = true;
-		}
-		Ok(())
-	}
+	/// An unquoted identifier, i.e. possibly a boolean.
+	Symbol(&'de str),
+
+	/// Any other token.
+	Value
 }
 
-impl<'a, 'de> SeqAccess<'de> for SExprTuple<'a, 'de> {
-	type Error = Error;
+impl<'de> Children<'de> {
+	/// Find all children of the current s-expr without consuming any input.
+	fn scan(de: &mut Deserializer<'de>) -> Result<Self> {
+		let start = de.input;
+		let mut items = Vec::new();
+		loop {
+			de.skip_whitespace();
+			let input = de.input;
+			let kind = match de.peek_char()? {
+				')' => break,
+				'(' => ChildKind::SExpr(de.peek_sexpr_identifier()?),
+				_ => match de.peek_identifier() {
+					Some(ident) => ChildKind::Symbol(ident),
+					None => ChildKind::Value
+				}
+			};
+			de.skip_value()?;
 
-	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+			// an identifier is only a symbol if it is the whole token
+			let kind = match kind {
+				ChildKind::Symbol(ident)
+					if ident.len() != input.len() - de.input.len() =>
+				{
+					ChildKind::Value
+				},
+				kind => kind
+			};
+			items.push(Child {
+				input,
+				kind,
+				used: false
+			});
+		}
+		let close = de.input;
+		de.input = start;
+		Ok(Self { items, close })
+	}
+
+	fn next_value_seed<T>(
+		&mut self,
+		de: &mut Deserializer<'de>,
+		fields: &'static [&'static str],
+		index: usize,
+		seed: T
+	) -> Result<T::Value>
 	where
 		T: DeserializeSeed<'de>
 	{
-		self.check_eoe()?;
-		if self.end {
-			return Ok(None);
+		let field = fields[index];
+		if field.is_empty() {
+			return seed.deserialize(Remaining {
+				de,
+				children: self,
+				capture: fields.contains(&EXTRA_FIELD),
+				index: 0
+			});
+		}
+		if let Some(name) = collection_name(field) {
+			let items: Vec<_> = self
+				.items
+				.iter_mut()
+				.filter(|child| {
+					!child.used
+						&& matches!(child.kind, ChildKind::SExpr(child) if child == name)
+				})
+				.map(|child| {
+					child.used = true;
+					child.input
+				})
+				.collect();
+			return seed.deserialize(Collected {
+				de,
+				items: items.into_iter(),
+				index: 0
+			});
 		}
-		let value = seed.deserialize(Field::new(self.de, None))?;
-		self.check_eoe()?;
-		Ok(Some(value))
-	}
-}
 
-/// Deserialize a field's ident.
-struct FieldIdent<'a>(&'a str);
+		let mut named = self.items.iter_mut().filter(|child| {
+			!child.used
+				&& match child.kind {
+					ChildKind::SExpr(name) | ChildKind::Symbol(name) => {
+						name == field
+					},
+					ChildKind::Value => false
+				}
+		});
+		if let Some(child) = named.next() {
+			child.used = true;
+			de.input = child.input;
+			if let ChildKind::Symbol(_) = child.kind {
+				return seed.deserialize(TrueField);
+			}
 
-impl<'a, 'de> de::Deserializer<'de> for FieldIdent<'a>
-where
-	'a: 'de
-{
-	type Error = Error;
+			// a map uses all children with this name, not just the first one
+			let rest: Vec<_> = named.collect();
+			let entries = std::iter::once(child.input)
+				.chain(rest.iter().map(|child| child.input))
+				.collect();
+			let report = Report::default();
+			let field = Field::new(de, Some(field))
+				.with_entries(entries)
+				.reporting_to(&report);
+			let value = seed.deserialize(field)?;
+			if report.entries_used.get() {
+				for child in rest {
+					child.used = true;
+				}
+			}
+			return Ok(value);
+		}
+
+		// values without a name are matched in order, but symbols that are named
+		// like a field are reserved for that field
+		let positional = self.items.iter_mut().find(|child| {
+			!child.used
+				&& match child.kind {
+					ChildKind::SExpr(_) => false,
+					ChildKind::Symbol(name) => !fields.contains(&name),
+					ChildKind::Value => true
+				}
+		});
+		match positional {
+			Some(child) => {
+				de.input = child.input;
+				let value = seed.deserialize(Field::new(de, Some(field)))?;
+				child.used = de.input.len() != child.input.len();
+				Ok(value)
+			},
+			None => {
+				de.input = self.close;
+				seed.deserialize(Field::new(de, Some(field)))
+			}
+		}
+	}
+
+	/// Return all unused s-exprs and their positions, and mark them as used. Also
+	/// return the positions of the children of every collection field.
+	#[allow(clippy::type_complexity)]
+	fn take_extra(
+		&mut self,
+		fields: &'static [&'static str]
+	) -> (Vec<(usize, &'de str)>, Vec<(&'static str, Vec<usize>)>) {
+		let items = self
+			.items
+			.iter_mut()
+			.enumerate()
+			.filter(|(_, child)| {
+				!child.used && matches!(child.kind, ChildKind::SExpr(_))
+			})
+			.map(|(index, child)| {
+				child.used = true;
+				(index, child.input)
+			})
+			.collect();
+		let collected = fields
+			.iter()
+			.filter_map(|field| collection_name(field))
+			.map(|name| {
+				let positions = self
+					.items
+					.iter()
+					.enumerate()
+					.filter(|(_, child)| match child.kind {
+						ChildKind::SExpr(child) => child == name,
+						_ => false
+					})
+					.map(|(index, _)| index)
+					.collect();
+				(name, positions)
+			})
+			.collect();
+		(items, collected)
+	}
+
+	/// Make sure that all children were used, and consume the s-expr. Unused s-exprs
+	/// are skipped if the deserializer is lenient.
+	fn finish(&self, de: &mut Deserializer<'de>) -> Result<()> {
+		let unused = self.items.iter().find(|child| {
+			let unknown = de.lenient && matches!(child.kind, ChildKind::SExpr(_));
+			!(child.used || unknown)
+		});
+		if let Some(child) = unused {
+			de.input = child.input;
+			bail!(ExpectedEoe);
+		}
+		de.input = &self.close[')'.len_utf8()..];
+		Ok(())
+	}
+}
+
+/// Deserialize the children that were not matched to any field as a sequence.
+struct Remaining<'a, 'de> {
+	de: &'a mut Deserializer<'de>,
+	children: &'a mut Children<'de>,
+
+	/// Set to true if unknown children are kept as extra children instead of
+	/// failing.
+	capture: bool,
+
+	index: usize
+}
+
+impl<'a, 'de> de::Deserializer<'de> for Remaining<'a, 'de> {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_seq(self)
+	}
+
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+impl<'a, 'de> SeqAccess<'de> for Remaining<'a, 'de> {
+	type Error = Error;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		let mut unused = self.children.items.iter_mut().filter(|child| !child.used);
+		let Some(child) = unused.next() else {
+			return Ok(None);
+		};
+		self.de.input = child.input;
+
+		// unknown children are skipped in front of the element, like in a
+		// collection whose children are read in order
+		let siblings = unused
+			.map(|child| child.input)
+			.chain(std::iter::once(self.children.close))
+			.collect();
+		let report = Report::default();
+		let field = Field::new(self.de, None)
+			.skipping_unknown(Some(&[]), self.capture)
+			.with_siblings(siblings)
+			.reporting_to(&report);
+		let value = seed.deserialize(field);
+		if report.ended.get() {
+			return Ok(None);
+		}
+		let value = value.map_err(|err| err.within_element(self.index))?;
+		if let Some(child) = self
+			.children
+			.items
+			.iter_mut()
+			.filter(|child| !child.used)
+			.nth(report.skipped.get())
+		{
+			child.used = true;
+		}
+		self.index += 1;
+		Ok(Some(value))
+	}
+}
+
+/// Deserialize the children that a collection field found as a sequence.
+struct Collected<'a, 'de> {
+	de: &'a mut Deserializer<'de>,
+	items: std::vec::IntoIter<&'de str>,
+	index: usize
+}
+
+impl<'a, 'de> de::Deserializer<'de> for Collected<'a, 'de> {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_seq(self)
+	}
+
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+impl<'a, 'de> SeqAccess<'de> for Collected<'a, 'de> {
+	type Error = Error;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		let Some(input) = self.items.next() else {
+			return Ok(None);
+		};
+		self.de.input = input;
+		let value = seed
+			.deserialize(Field::new(self.de, None))
+			.map_err(|err| err.within_element(self.index))?;
+		self.index += 1;
+		Ok(Some(value))
+	}
+}
+
+/// Deserialize the unknown children of an s-expr as a sequence of their positions
+/// and values.
+struct Captured<'a, 'de> {
+	de: &'a mut Deserializer<'de>,
+	items: std::vec::IntoIter<(usize, &'de str)>,
+
+	/// The positions of the children of every collection field, which follow the
+	/// unknown children.
+	collected: std::vec::IntoIter<(&'static str, Vec<usize>)>
+}
+
+impl<'a, 'de> de::Deserializer<'de> for Captured<'a, 'de> {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_seq(self)
+	}
+
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+impl<'a, 'de> SeqAccess<'de> for Captured<'a, 'de> {
+	type Error = Error;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		let Some((index, input)) = self.items.next() else {
+			let Some((name, positions)) = self.collected.next() else {
+				return Ok(None);
+			};
+			return seed
+				.deserialize(CapturedCollected {
+					name: Some(name),
+					positions: Some(positions)
+				})
+				.map(Some);
+		};
+		let rest = self.de.input;
+		let value = seed.deserialize(CapturedChild {
+			de: self.de,
+			index: Some(index),
+			input: Some(input)
+		})?;
+		self.de.input = rest;
+		Ok(Some(value))
+	}
+}
+
+/// Deserialize an unknown child as a tuple of its position and value.
+struct CapturedChild<'a, 'de> {
+	de: &'a mut Deserializer<'de>,
+	index: Option<usize>,
+	input: Option<&'de str>
+}
+
+impl<'a, 'de> de::Deserializer<'de> for CapturedChild<'a, 'de> {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_seq(self)
+	}
+
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+impl<'a, 'de> SeqAccess<'de> for CapturedChild<'a, 'de> {
+	type Error = Error;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		if let Some(index) = self.index.take() {
+			return seed.deserialize(index.into_deserializer()).map(Some);
+		}
+		let Some(input) = self.input.take() else {
+			return Ok(None);
+		};
+		self.de.input = input;
+		seed.deserialize(Field::new(self.de, None)).map(Some)
+	}
+}
+
+/// Deserialize the positions of the children of a collection field as a tuple of
+/// the name of the children and their positions.
+struct CapturedCollected {
+	name: Option<&'static str>,
+	positions: Option<Vec<usize>>
+}
+
+impl<'de> de::Deserializer<'de> for CapturedCollected {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_seq(self)
+	}
+
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+impl<'de> SeqAccess<'de> for CapturedCollected {
+	type Error = Error;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		if let Some(name) = self.name.take() {
+			return seed.deserialize(name.into_deserializer()).map(Some);
+		}
+		match self.positions.take() {
+			Some(positions) => seed
+				.deserialize(SeqDeserializer::new(positions.into_iter()))
+				.map(Some),
+			None => Ok(None)
+		}
+	}
+}
+
+/// Deserialize an s-expr in tuple format. It cannot contain booleans.
+struct SExprTuple<'a, 'de> {
+	de: &'a mut Deserializer<'de>,
+
+	/// The name of the s-expr if it still needs to be returned as the first element.
+	head: Option<&'de str>,
+
+	index: usize,
+	end: bool
+}
+
+impl<'a, 'de> SExprTuple<'a, 'de> {
+	fn new(de: &'a mut Deserializer<'de>, name: &'static str) -> Result<Self> {
+		SExpr::consume_beginning(de, name)?;
+		Ok(Self::body(de))
+	}
+
+	/// Deserialize the content of an s-expr whose beginning was already consumed.
+	fn body(de: &'a mut Deserializer<'de>) -> Self {
+		Self {
+			de,
+			head: None,
+			index: 0,
+			end: false
+		}
+	}
+
+	/// Deserialize an s-expr with any name. The name is returned as the first element,
+	/// followed by the values of the s-expr.
+	fn untyped(de: &'a mut Deserializer<'de>) -> Result<Self> {
+		let head = de.peek_sexpr_identifier()?;
+		de.consume('('.len_utf8() + head.len())?;
+		Ok(Self {
+			de,
+			head: Some(head),
+			index: 0,
+			end: false
+		})
+	}
+
+	fn check_eoe(&mut self) -> Result<()> {
+		if self.end {
+			return Ok(());
+		}
+
+		self.de.skip_whitespace();
+		if self.de.peek_char()? == ')' {
+			self.de.consume(')'.len_utf8())?;
+			self.end = true;
+		}
+		Ok(())
+	}
+}
+
+impl<'a, 'de> SeqAccess<'de> for SExprTuple<'a, 'de> {
+	type Error = Error;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		if let Some(head) = self.head.take() {
+			return seed.deserialize(FieldIdent(head)).map(Some);
+		}
+
+		self.check_eoe()?;
+		if self.end {
+			return Ok(None);
+		}
+		let report = Report::default();
+		let field = Field::new(self.de, None)
+			.skipping_unknown(Some(&[]), false)
+			.reporting_to(&report);
+		let value = seed.deserialize(field);
+		if report.ended.get() {
+			self.check_eoe()?;
+			return Ok(None);
+		}
+		let value = value.map_err(|err| err.within_index(self.index))?;
+		self.index += 1;
+		self.check_eoe()?;
+		Ok(Some(value))
+	}
+}
+
+/// Deserialize a field's ident, or any other symbol.
+struct FieldIdent<'a>(&'a str);
+
+impl<'a, 'de> de::Deserializer<'de> for FieldIdent<'a>
+where
+	'a: 'de
+{
+	type Error = Error;
 
 	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
 	where
@@ -574,71 +1613,675 @@ where
 	}
 }
 
-/// Deserialize a single boolean with value `true`.
-struct TrueField;
+/// Deserialize a single boolean with value `true`.
+struct TrueField;
+
+impl<'de> de::Deserializer<'de> for TrueField {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_bool(true)
+	}
+
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+/// Deserialize either a boolean with value `false`, an option with value `None` or
+/// an empty map.
+struct MissingField;
+
+impl<'de> de::Deserializer<'de> for MissingField {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_none()
+	}
+
+	fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_bool(false)
+	}
+
+	fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		let entries = std::iter::empty::<((), ())>();
+		visitor.visit_map(MapDeserializer::new(entries))
+	}
+
+	forward_to_deserialize_any! {
+		i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct struct enum identifier ignored_any
+	}
+}
+
+/// What reading a field found out about the children around it, besides its
+/// value. The caller owns the report and hands it to the field.
+#[derive(Debug, Default)]
+struct Report {
+	/// Set if only unknown s-exprs were left in the collection, so that there is no
+	/// element to read.
+	ended: Cell<bool>,
+
+	/// The number of unknown children that were skipped in front of the element,
+	/// if the children of the surrounding s-expr are matched by name.
+	skipped: Cell<usize>,
+
+	/// Set if a map used all children that are named like its field.
+	entries_used: Cell<bool>
+}
+
+/// A field whose value does not match its ident. This means that if a boolean gets requested,
+/// we must return false without touching the input.
+///
+/// We still store the ident if we know it, so that we can parse a sequence like
+/// (<ident> <values..>). The empty ident (`""`) is treated as a special case to consume
+/// the remaining fields of the current expression.
+struct Field<'a, 'de> {
+	de: &'a mut Deserializer<'de>,
+	ident: Option<&'static str>,
+
+	/// The idents of the fields after this one, used to tell if an option is present.
+	later: &'static [&'static str],
+
+	/// The names of the s-exprs that are known in the surrounding s-expr, or `None`
+	/// if unknown s-exprs must not be skipped in front of this value.
+	known: Option<&'static [&'static str]>,
+
+	/// Set to true if unknown s-exprs are kept as extra children of the surrounding
+	/// s-expr.
+	capture: bool,
+
+	/// The input of every child named like this field, if the children of the
+	/// surrounding s-expr are matched by name.
+	entries: Option<Vec<&'de str>>,
+
+	/// The input of the unused children after this element and of the closing
+	/// parenthesis, if the children of the surrounding s-expr are matched by name.
+	siblings: Option<std::vec::IntoIter<&'de str>>,
+
+	/// The report of the collection that this field is an element of, or of the
+	/// s-expr whose children it was given.
+	report: Option<&'a Report>,
+
+	/// The index of the next element when consuming the remaining fields.
+	index: usize
+}
+
+impl<'a, 'de> Field<'a, 'de> {
+	fn new(de: &'a mut Deserializer<'de>, ident: Option<&'static str>) -> Self {
+		Self {
+			de,
+			ident,
+			later: &[],
+			known: None,
+			capture: false,
+			entries: None,
+			siblings: None,
+			report: None,
+			index: 0
+		}
+	}
+
+	fn followed_by(mut self, later: &'static [&'static str]) -> Self {
+		self.later = later;
+		self
+	}
+
+	fn skipping_unknown(
+		mut self,
+		known: Option<&'static [&'static str]>,
+		capture: bool
+	) -> Self {
+		self.known = known;
+		self.capture = capture;
+		self
+	}
+
+	fn with_entries(mut self, entries: Vec<&'de str>) -> Self {
+		self.entries = Some(entries);
+		self
+	}
+
+	fn with_siblings(mut self, siblings: Vec<&'de str>) -> Self {
+		self.siblings = Some(siblings.into_iter());
+		self
+	}
+
+	fn reporting_to(mut self, report: &'a Report) -> Self {
+		self.report = Some(report);
+		self
+	}
+
+	/// Visit the next token as a symbol. Symbols are marked as newtypes so that they
+	/// can be told apart from quoted strings.
+	fn visit_symbol<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		match self.de.parse_string()? {
+			Cow::Borrowed(symbol) => visitor.visit_newtype_struct(FieldIdent(symbol)),
+			Cow::Owned(symbol) => {
+				visitor.visit_newtype_struct(symbol.into_deserializer())
+			},
+		}
+	}
+
+	/// Skip unknown s-exprs in front of a value that is named like one of `names`.
+	/// If only unknown s-exprs were left in a collection, report that it ended
+	/// instead of reading a value.
+	fn skip_to(&mut self, names: &[&str]) -> Result<()> {
+		if self.skip_unknown(names)? {
+			if let Some(report) = self.report {
+				report.ended.set(true);
+			}
+			bail!(ExpectedSExpr(')'));
+		}
+		Ok(())
+	}
+
+	/// Skip unknown s-exprs in front of a value that is named like one of `names`.
+	/// Return true if only unknown s-exprs were left in a collection.
+	fn skip_unknown(&mut self, names: &[&str]) -> Result<bool> {
+		let Some(known) = self.known else {
+			return Ok(false);
+		};
+		let is_known = |name: &str| names.contains(&name) || known.contains(&name);
+		let skipped = match &mut self.siblings {
+			// the skipped children are left unused, which keeps them as extra
+			// children if they are captured
+			Some(siblings) => {
+				let mut skipped = false;
+				while self.de.lenient || self.capture {
+					self.de.skip_whitespace();
+					if self.de.peek_char()? != '('
+						|| is_known(self.de.peek_sexpr_identifier()?)
+					{
+						break;
+					}
+					let Some(next) = siblings.next() else {
+						break;
+					};
+					self.de.input = next;
+					if let Some(report) = self.report {
+						report.skipped.set(report.skipped.get() + 1);
+					}
+					skipped = true;
+				}
+				skipped
+			},
+			None => self.de.skip_unknown(is_known, self.capture)?
+		};
+		Ok(skipped && self.ident.is_none() && self.de.peek_char()? == ')')
+	}
+}
+
+macro_rules! forward_to_parse_number {
+	($($ident:ident)+) => {
+		$(
+			paste! {
+				fn [<deserialize_ $ident>]<V>(self, visitor: V) -> Result<V::Value>
+				where
+					V: Visitor<'de>
+				{
+					visitor.[<visit_ $ident>](self.de.parse_number()?)
+				}
+			}
+		)+
+	};
+}
+
+impl<'a, 'de> de::Deserializer<'de> for Field<'a, 'de> {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		match self.de.peek_token()? {
+			// integers that are too large are read as symbols, so that none of their
+			// digits are lost
+			Token::Int if self.de.peek_large_int() => self.visit_symbol(visitor),
+			Token::Int if self.de.peek_char()? == '-' => {
+				self.deserialize_i64(visitor)
+			},
+			Token::Int => self.deserialize_u64(visitor),
+			Token::Float => self.deserialize_f64(visitor),
+			Token::String if self.de.peek_char()? == '"' => {
+				self.deserialize_string(visitor)
+			},
+			Token::String => self.visit_symbol(visitor),
+			Token::SExpr => visitor.visit_seq(SExprTuple::untyped(self.de)?)
+		}
+	}
+
+	fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_bool(false)
+	}
+
+	fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.deserialize_string(visitor)
+	}
+
+	fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		let value = self.de.parse_string()?;
+		match value {
+			Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+			Cow::Owned(value) => visitor.visit_string(value)
+		}
+	}
+
+	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		// serde doesn't tell us the type inside the option, so we can only guess from the
+		// next token: An s-expr is present if it is named like this field, and missing if
+		// it belongs to a later field or the remaining fields.
+		self.de.skip_whitespace();
+		let present = match self.de.peek_char() {
+			Err(_) | Ok(')') => false,
+			Ok('(') => {
+				let name = self.de.peek_sexpr_identifier()?;
+				Some(name) == self.ident
+					|| !self
+						.later
+						.iter()
+						.any(|field| field.is_empty() || *field == name)
+			},
+			Ok(_) => true
+		};
+
+		match present {
+			true => visitor.visit_some(self),
+			false => visitor.visit_none()
+		}
+	}
+
+	fn deserialize_struct<V>(
+		mut self,
+		name: &'static str,
+		fields: &'static [&'static str],
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.skip_to(&[name])?;
+		visitor
+			.visit_map(SExpr::new(self.de, name, fields)?)
+			.map_err(|err| err.within_sexpr(name))
+	}
+
+	fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		let ident = match self.ident {
+			Some(ident) => ident,
+			None => {
+				bail!(MissingSExprInfo(
+					self.de.peek_sexpr_identifier()?.to_owned()
+				));
+			}
+		};
+		self.deserialize_unit_struct(ident, visitor)
+	}
+
+	fn deserialize_unit_struct<V>(
+		mut self,
+		name: &'static str,
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.skip_to(&[name])?;
+		SExpr::consume_unit(self.de, name)?;
+		visitor.visit_unit()
+	}
+
+	fn deserialize_newtype_struct<V>(
+		self,
+		name: &'static str,
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		match name {
+			EXTRA => {
+				bail!(MisplacedExtra);
+			},
+			VALUE => visitor.visit_newtype_struct(self),
+			_ => self.deserialize_tuple_struct(name, 1, visitor)
+		}
+	}
+
+	fn deserialize_tuple_struct<V>(
+		mut self,
+		name: &'static str,
+		_len: usize,
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.skip_to(&[name])?;
+		visitor
+			.visit_seq(SExprTuple::new(self.de, name)?)
+			.map_err(|err| err.within_sexpr(name))
+	}
+
+	fn deserialize_enum<V>(
+		mut self,
+		name: &'static str,
+		variants: &'static [&'static str],
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if name == TAGGED {
+			// unknown s-exprs can only be skipped if the names of all variants
+			// are known
+			let (path, _, names) = split_tagged(variants);
+			if !names.contains(&TAGGED) {
+				self.skip_to(names)?;
+			}
+			let tag = self.de.peek_tag(path)?;
+			return visitor.visit_enum(TaggedEnum { tag, content: self });
+		}
+		self.skip_to(variants)?;
+		visitor.visit_enum(FieldEnum {
+			field: self,
+			variants
+		})
+	}
+
+	fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		let ident = match self.ident {
+			Some(ident) => ident,
+			None => {
+				bail!(MissingSExprInfo(
+					self.de.peek_sexpr_identifier()?.to_owned()
+				));
+			}
+		};
+		match ident {
+			"" => {
+				// special case: we'll return the remaining tokens of the current s-expr
+				visitor.visit_seq(self)
+			},
+			_ => {
+				self.skip_to(&[ident])?;
+				visitor
+					.visit_seq(SExprTuple::new(self.de, ident)?)
+					.map_err(|err| err.within_sexpr(ident))
+			}
+		}
+	}
+
+	fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_map<V>(mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		let name = match self.ident {
+			Some(ident) if !ident.is_empty() => ident,
+			_ => {
+				bail!(MissingSExprInfo(
+					self.de.peek_sexpr_identifier()?.to_owned()
+				));
+			}
+		};
+		let entries = self.entries.take().map(Vec::into_iter);
+		visitor.visit_map(Entries {
+			field: self,
+			name,
+			entries,
+			index: 0
+		})
+	}
+
+	forward_to_parse_number! {
+		i8 i16 i32 i64 i128 u8 u16 u32 u64 u128
+	}
+
+	fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_f32(self.de.parse_float(f32::is_finite)?)
+	}
+
+	fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_f64(self.de.parse_float(f64::is_finite)?)
+	}
+
+	fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.deserialize_string(visitor)
+	}
+
+	fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.de.skip_value()?;
+		visitor.visit_unit()
+	}
+
+	forward_to_deserialize_any! {
+		char bytes byte_buf
+	}
+}
+
+impl<'a, 'de> SeqAccess<'de> for Field<'a, 'de> {
+	type Error = Error;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		self.de.skip_whitespace();
+		if self.de.peek_char()? == ')' {
+			return Ok(None);
+		}
+		let report = Report::default();
+		let field = Field::new(self.de, None)
+			.skipping_unknown(self.known, self.capture)
+			.reporting_to(&report);
+		let value = seed.deserialize(field);
+		if report.ended.get() {
+			return Ok(None);
+		}
+		let value = value.map_err(|err| err.within_element(self.index))?;
+		self.index += 1;
+		Ok(Some(value))
+	}
+}
+
+/// Deserialize an enum in a field. Unit variants are symbols, and all other
+/// variants are s-exprs.
+struct FieldEnum<'a, 'de> {
+	field: Field<'a, 'de>,
+	variants: &'static [&'static str]
+}
 
-impl<'de> de::Deserializer<'de> for TrueField {
+impl<'a, 'de> EnumAccess<'de> for FieldEnum<'a, 'de> {
 	type Error = Error;
+	type Variant = Either<UnitVariant, SExprVariant<'a, 'de>>;
 
-	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	fn variant_seed<V>(mut self, seed: V) -> Result<(V::Value, Self::Variant)>
 	where
-		V: Visitor<'de>
+		V: DeserializeSeed<'de>
 	{
-		visitor.visit_bool(true)
+		Ok(match self.field.de.peek_token()? {
+			Token::SExpr => {
+				let str = self.field.de.peek_sexpr_identifier()?;
+				(
+					seed.deserialize(FieldIdent(str))?,
+					Either::Right(SExprVariant {
+						de: self.field.de,
+						variants: self.variants
+					})
+				)
+			},
+			token => match self.untagged_variant(token)? {
+				Some(name) => (
+					seed.deserialize(FieldIdent(name))?,
+					Either::Right(SExprVariant {
+						de: self.field.de,
+						variants: self.variants
+					})
+				),
+				None => (seed.deserialize(self.field)?, Either::Left(UnitVariant))
+			}
+		})
 	}
+}
 
-	forward_to_deserialize_any! {
-		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-		bytes byte_buf option unit unit_struct newtype_struct seq tuple
-		tuple_struct map struct enum identifier ignored_any
+impl<'a, 'de> FieldEnum<'a, 'de> {
+	/// Find the variant of an [`untagged!`](crate::untagged) enum that wraps the
+	/// plain value at the start of the input. Symbols that are the name of a unit
+	/// variant are left to the unit variant.
+	fn untagged_variant(&mut self, token: Token) -> Result<Option<&'static str>> {
+		let candidates: &[&'static str] = match token {
+			Token::Int => &[UNTAGGED_INT, UNTAGGED_FLOAT, UNTAGGED_STRING],
+			Token::Float => &[UNTAGGED_FLOAT, UNTAGGED_STRING],
+			_ => &[UNTAGGED_STRING]
+		};
+		let name = candidates
+			.iter()
+			.find(|name| self.variants.contains(name));
+		let name = match name {
+			Some(name) => *name,
+			None => return Ok(None)
+		};
+
+		if matches!(token, Token::String) && self.field.de.peek_char()? != '"' {
+			let de = &mut *self.field.de;
+			let input = de.input;
+			let symbol = de.parse_string()?;
+			de.input = input;
+			if self.variants.contains(&&*symbol) {
+				return Ok(None);
+			}
+		}
+		Ok(Some(name))
 	}
 }
 
-/// Deserialize either a boolean with value `false` or an option with value `None`.
-struct MissingField;
+/// Deserialize a map from the repeated s-exprs named like its field. The first
+/// value of every s-expr is the key, and the remaining values are the value.
+struct Entries<'a, 'de> {
+	field: Field<'a, 'de>,
+	name: &'static str,
 
-impl<'de> de::Deserializer<'de> for MissingField {
+	/// The input of every entry, if the children of the surrounding s-expr are
+	/// matched by name. Otherwise, the entries are read as long as they follow
+	/// each other.
+	entries: Option<std::vec::IntoIter<&'de str>>,
+
+	index: usize
+}
+
+impl<'a, 'de> MapAccess<'de> for Entries<'a, 'de> {
 	type Error = Error;
 
-	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
 	where
-		V: Visitor<'de>
+		K: DeserializeSeed<'de>
 	{
-		visitor.visit_none()
+		match &mut self.entries {
+			Some(entries) => match entries.next() {
+				Some(input) => self.field.de.input = input,
+				None => {
+					if let Some(report) = self.field.report {
+						report.entries_used.set(true);
+					}
+					return Ok(None);
+				}
+			},
+			None => {
+				self.field.skip_to(&[self.name])?;
+				self.field.de.skip_whitespace();
+				if self.field.de.peek_char()? != '('
+					|| self.field.de.peek_sexpr_identifier()? != self.name
+				{
+					return Ok(None);
+				}
+			}
+		}
+
+		let name = self.name;
+		let index = self.index;
+		SExpr::consume_beginning(self.field.de, name)?;
+		self.field.de.skip_whitespace();
+		seed.deserialize(Field::new(self.field.de, None))
+			.map(Some)
+			.map_err(|err| err.within_sexpr(name).within_element(index))
 	}
 
-	fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+	fn next_value_seed<T>(&mut self, seed: T) -> Result<T::Value>
 	where
-		V: Visitor<'de>
+		T: DeserializeSeed<'de>
 	{
-		visitor.visit_bool(false)
-	}
-
-	forward_to_deserialize_any! {
-		i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-		bytes byte_buf option unit unit_struct newtype_struct seq tuple
-		tuple_struct map struct enum identifier ignored_any
+		let name = self.name;
+		let index = self.index;
+		let value = seed
+			.deserialize(EntryValue { de: self.field.de })
+			.map_err(|err| err.within_sexpr(name).within_element(index))?;
+		self.index += 1;
+		Ok(value)
 	}
 }
 
-/// A field whose value does not match its ident. This means that if a boolean gets requested,
-/// we must return false without touching the input.
-///
-/// We still store the ident if we know it, so that we can parse a sequence like
-/// (<ident> <values..>). The empty ident (`""`) is treated as a special case to consume
-/// the remaining fields of the current expression.
-struct Field<'a, 'de> {
-	de: &'a mut Deserializer<'de>,
-	ident: Option<&'static str>
-}
-
-impl<'a, 'de> Field<'a, 'de> {
-	fn new(de: &'a mut Deserializer<'de>, ident: Option<&'static str>) -> Self {
-		Self { de, ident }
-	}
+/// Deserialize the value of a map entry, i.e. the remaining content of its s-expr.
+/// Structs and tuples are read without an s-expr of their own.
+struct EntryValue<'a, 'de> {
+	de: &'a mut Deserializer<'de>
 }
 
-macro_rules! forward_to_parse_number {
+macro_rules! forward_to_field {
 	($($ident:ident)+) => {
 		$(
 			paste! {
@@ -646,218 +2289,184 @@ macro_rules! forward_to_parse_number {
 				where
 					V: Visitor<'de>
 				{
-					visitor.[<visit_ $ident>](self.de.parse_number()?)
+					self.de.skip_whitespace();
+					let value = Field::new(self.de, None)
+						.[<deserialize_ $ident>](visitor)?;
+					SExpr::consume_end(self.de)?;
+					Ok(value)
 				}
 			}
 		)+
 	};
 }
 
-impl<'a, 'de> de::Deserializer<'de> for Field<'a, 'de> {
+impl<'a, 'de> de::Deserializer<'de> for EntryValue<'a, 'de> {
 	type Error = Error;
 
-	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
-	where
-		V: Visitor<'de>
-	{
-		match self.de.peek_token()? {
-			Token::Int if self.de.peek_char()? == '-' => {
-				self.deserialize_i64(visitor)
-			},
-			Token::Int => self.deserialize_u64(visitor),
-			Token::Float => self.deserialize_f64(visitor),
-			Token::String => self.deserialize_string(visitor),
-			Token::SExpr if Some(self.de.peek_sexpr_identifier()?) == self.ident => {
-				self.deserialize_seq(visitor)
-			},
-			Token::SExpr => Err(error!(MissingSExprInfo(
-				self.de.peek_sexpr_identifier()?.to_owned()
-			)))
-		}
-	}
-
-	fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
-	where
-		V: Visitor<'de>
-	{
-		visitor.visit_bool(false)
-	}
-
-	fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
-	where
-		V: Visitor<'de>
-	{
-		self.deserialize_string(visitor)
+	forward_to_field! {
+		any bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option map identifier ignored_any
 	}
 
-	fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+	fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
 	where
 		V: Visitor<'de>
 	{
-		let value = self.de.parse_string()?;
-		match value {
-			Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
-			Cow::Owned(value) => visitor.visit_string(value)
-		}
+		SExpr::consume_end(self.de)?;
+		visitor.visit_unit()
 	}
 
-	fn deserialize_option<V>(self, _visitor: V) -> Result<V::Value>
+	fn deserialize_unit_struct<V>(
+		self,
+		_name: &'static str,
+		visitor: V
+	) -> Result<V::Value>
 	where
 		V: Visitor<'de>
 	{
-		// we'll need to know the type of Some (i.e. the s-expr tag) to see if it is present in
-		// the input or not
-		// however, serde doesn't give us this type of information, so we'll just error
-		Err(error!(DeserializeOption))
+		self.deserialize_unit(visitor)
 	}
 
-	fn deserialize_struct<V>(
+	fn deserialize_newtype_struct<V>(
 		self,
 		name: &'static str,
-		fields: &'static [&'static str],
 		visitor: V
 	) -> Result<V::Value>
 	where
 		V: Visitor<'de>
 	{
-		visitor.visit_map(SExpr::new(self.de, name, fields)?)
+		if name == VALUE {
+			return visitor.visit_newtype_struct(self);
+		}
+		self.deserialize_seq(visitor)
 	}
 
-	fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
 	where
 		V: Visitor<'de>
 	{
-		let ident = match self.ident {
-			Some(ident) => ident,
-			None => {
-				bail!(MissingSExprInfo(
-					self.de.peek_sexpr_identifier()?.to_owned()
-				));
-			}
-		};
-		self.deserialize_unit_struct(ident, visitor)
+		visitor.visit_seq(SExprTuple::body(self.de))
 	}
 
-	fn deserialize_unit_struct<V>(
-		self,
-		name: &'static str,
-		visitor: V
-	) -> Result<V::Value>
+	fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
 	where
 		V: Visitor<'de>
 	{
-		SExpr::consume_beginning(self.de, name)?;
-		if self.de.next_char()? != ')' {
-			bail!(ExpectedEoe);
-		}
-		visitor.visit_unit()
+		self.deserialize_seq(visitor)
 	}
 
-	fn deserialize_newtype_struct<V>(
+	fn deserialize_tuple_struct<V>(
 		self,
-		name: &'static str,
+		_name: &'static str,
+		_len: usize,
 		visitor: V
 	) -> Result<V::Value>
 	where
 		V: Visitor<'de>
 	{
-		self.deserialize_tuple_struct(name, 1, visitor)
+		self.deserialize_seq(visitor)
 	}
 
-	fn deserialize_tuple_struct<V>(
+	fn deserialize_struct<V>(
 		self,
 		name: &'static str,
-		_len: usize,
+		fields: &'static [&'static str],
 		visitor: V
 	) -> Result<V::Value>
 	where
 		V: Visitor<'de>
 	{
-		visitor.visit_seq(SExprTuple::new(self.de, name)?)
+		visitor.visit_map(SExpr::body(self.de, name, fields)?)
 	}
 
 	fn deserialize_enum<V>(
 		self,
-		_name: &'static str,
-		_variants: &'static [&'static str],
+		name: &'static str,
+		variants: &'static [&'static str],
 		visitor: V
 	) -> Result<V::Value>
 	where
 		V: Visitor<'de>
 	{
-		visitor.visit_enum(self)
+		self.de.skip_whitespace();
+		let value =
+			Field::new(self.de, None).deserialize_enum(name, variants, visitor)?;
+		SExpr::consume_end(self.de)?;
+		Ok(value)
 	}
+}
 
-	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+/// Deserialize an enum whose variant is selected by a value inside of the s-expr.
+/// The s-expr itself is deserialized by `content` as the variant's newtype.
+struct TaggedEnum<'de, D> {
+	tag: Cow<'de, str>,
+	content: D
+}
+
+impl<'de, D> EnumAccess<'de> for TaggedEnum<'de, D>
+where
+	D: de::Deserializer<'de, Error = Error>
+{
+	type Error = Error;
+	type Variant = TaggedVariant<D>;
+
+	fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
 	where
-		V: Visitor<'de>
+		V: DeserializeSeed<'de>
 	{
-		let ident = match self.ident {
-			Some(ident) => ident,
-			None => {
-				bail!(MissingSExprInfo(
-					self.de.peek_sexpr_identifier()?.to_owned()
-				));
-			}
+		let value = match self.tag {
+			Cow::Borrowed(tag) => seed.deserialize(FieldIdent(tag))?,
+			Cow::Owned(tag) => seed.deserialize(tag.into_deserializer())?
 		};
-		match ident {
-			"" => {
-				// special case: we'll return the remaining tokens of the current s-expr
-				visitor.visit_seq(self)
-			},
-			_ => visitor.visit_seq(SExprTuple::new(self.de, ident)?)
-		}
+		Ok((value, TaggedVariant(self.content)))
 	}
+}
 
-	fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
-	where
-		V: Visitor<'de>
-	{
-		self.deserialize_seq(visitor)
-	}
+/// This will deserialize only newtype variants, whose content is the whole s-expr.
+struct TaggedVariant<D>(D);
 
-	forward_to_parse_number! {
-		i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64
-	}
+impl<'de, D> VariantAccess<'de> for TaggedVariant<D>
+where
+	D: de::Deserializer<'de, Error = Error>
+{
+	type Error = Error;
 
-	forward_to_deserialize_any! {
-		char bytes byte_buf map identifier ignored_any
+	fn unit_variant(self) -> Result<()> {
+		Err(de::Error::invalid_type(
+			de::Unexpected::NewtypeVariant,
+			&"unit variant"
+		))
 	}
-}
-
-impl<'a, 'de> SeqAccess<'de> for Field<'a, 'de> {
-	type Error = Error;
 
-	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
 	where
 		T: DeserializeSeed<'de>
 	{
-		self.de.skip_whitespace();
-		if self.de.peek_char()? == ')' {
-			return Ok(None);
-		}
-		seed.deserialize(Field::new(self.de, None)).map(Some)
+		seed.deserialize(self.0)
 	}
-}
 
-impl<'a, 'de> EnumAccess<'de> for Field<'a, 'de> {
-	type Error = Error;
-	type Variant = Either<UnitVariant, NewtypeVariant<'a, 'de>>;
+	fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		Err(de::Error::invalid_type(
+			de::Unexpected::NewtypeVariant,
+			&visitor
+		))
+	}
 
-	fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+	fn struct_variant<V>(
+		self,
+		_fields: &'static [&'static str],
+		visitor: V
+	) -> Result<V::Value>
 	where
-		V: DeserializeSeed<'de>
+		V: Visitor<'de>
 	{
-		Ok(match self.de.peek_token()? {
-			Token::SExpr => {
-				let str = self.de.peek_sexpr_identifier()?;
-				(
-					seed.deserialize(FieldIdent(str))?,
-					Either::Right(NewtypeVariant { de: self.de })
-				)
-			},
-			_ => (seed.deserialize(self)?, Either::Left(UnitVariant))
-		})
+		Err(de::Error::invalid_type(
+			de::Unexpected::NewtypeVariant,
+			&visitor
+		))
 	}
 }
 
@@ -897,16 +2506,33 @@ impl<'de> VariantAccess<'de> for UnitVariant {
 	}
 }
 
-/// This will deserialize only newtype variants.
-struct NewtypeVariant<'a, 'de> {
-	de: &'a mut Deserializer<'de>
+/// This will deserialize all variants but unit variants. Newtype variants are
+/// named like their content, and tuple and struct variants are s-exprs named like
+/// the variant.
+struct SExprVariant<'a, 'de> {
+	de: &'a mut Deserializer<'de>,
+	variants: &'static [&'static str]
+}
+
+impl<'a, 'de> SExprVariant<'a, 'de> {
+	/// Consume the beginning of the s-expr and return the name of its variant.
+	fn consume_beginning(&mut self) -> Result<&'static str> {
+		self.de.skip_whitespace();
+		let peek = self.de.peek_sexpr_identifier()?;
+		let variant = self.variants.iter().find(|variant| **variant == peek);
+		let Some(name) = variant else {
+			return Err(de::Error::unknown_variant(peek, self.variants));
+		};
+		SExpr::consume_beginning(self.de, name)?;
+		Ok(name)
+	}
 }
 
-impl<'a, 'de> VariantAccess<'de> for NewtypeVariant<'a, 'de> {
+impl<'a, 'de> VariantAccess<'de> for SExprVariant<'a, 'de> {
 	type Error = Error;
 
 	fn unit_variant(self) -> Result<(), Self::Error> {
-		Err(error!(NonNewtypeEnumVariant))
+		Err(error!(UnitVariantInSExpr))
 	}
 
 	fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
@@ -917,25 +2543,31 @@ impl<'a, 'de> VariantAccess<'de> for NewtypeVariant<'a, 'de> {
 	}
 
 	fn tuple_variant<V>(
-		self,
+		mut self,
 		_len: usize,
-		_visitor: V
+		visitor: V
 	) -> Result<V::Value, Self::Error>
 	where
 		V: Visitor<'de>
 	{
-		Err(error!(NonNewtypeEnumVariant))
+		let name = self.consume_beginning()?;
+		visitor
+			.visit_seq(SExprTuple::body(self.de))
+			.map_err(|err| err.within_sexpr(name))
 	}
 
 	fn struct_variant<V>(
-		self,
-		_fields: &'static [&'static str],
-		_visitor: V
+		mut self,
+		fields: &'static [&'static str],
+		visitor: V
 	) -> Result<V::Value, Self::Error>
 	where
 		V: Visitor<'de>
 	{
-		Err(error!(NonNewtypeEnumVariant))
+		let name = self.consume_beginning()?;
+		visitor
+			.visit_map(SExpr::body(self.de, name, fields)?)
+			.map_err(|err| err.within_sexpr(name))
 	}
 }
 
@@ -0,0 +1,278 @@
+use crate::Value;
+use serde::{
+	de::{self, Deserialize, Deserializer, SeqAccess, Unexpected, Visitor},
+	ser::{Serialize, SerializeSeq, Serializer}
+};
+use std::fmt::{self, Formatter};
+
+/// The magic newtype struct name used to (de)serialize an [`Extra`].
+pub(crate) const EXTRA: &str = "$serde_kicad_sexpr::private::Extra";
+
+/// The name of the field that holds an [`Extra`].
+pub(crate) const EXTRA_FIELD: &str = "$extra";
+
+/// The children of an s-expr that were not matched to any field of its struct.
+///
+/// Every child is kept together with its position among all children of the
+/// s-expr, so that it can be written back at the same place. This allows reading
+/// and writing files that contain s-exprs your types don't know about, without
+/// losing them. Only s-exprs are kept, unknown values like numbers are still an
+/// error. Children that were [pushed](Self::push) have no position and are
+/// written after all others.
+///
+/// The positions of the children of fields whose name ends with a `*` are recorded
+/// as well, so that these children are written back in their original order
+/// instead of one field after the other. The n-th child of such a field is written
+/// at the position of the n-th child that was read, and any further children are
+/// written after all others.
+///
+/// To use this, add a field named `$extra` to your struct. This has to be the first
+/// field, because the unknown children need to be known before any other field is
+/// written.
+///
+/// ### Example
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use serde_kicad_sexpr::Extra;
+///
+/// #[derive(Deserialize, Serialize)]
+/// #[serde(rename = "size")]
+/// struct Size(f32, f32);
+///
+/// #[derive(Deserialize, Serialize)]
+/// #[serde(rename = "font")]
+/// struct Font {
+///     #[serde(default, rename = "$extra")]
+///     extra: Extra,
+///
+///     size: Size,
+///     bold: bool
+/// }
+///
+/// let input = r#"(font (face "KiCad Font") (size 1 1) bold)"#;
+/// let font: Font = serde_kicad_sexpr::from_str(input).unwrap();
+/// assert_eq!(font.extra.get("face").unwrap().items().len(), 1);
+/// assert_eq!(serde_kicad_sexpr::to_string(&font).unwrap(), input);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Extra {
+	children: Vec<(Option<usize>, Value)>,
+
+	/// The positions of the children of every collection field, if they were read.
+	collected: Vec<(String, Vec<usize>)>
+}
+
+impl Extra {
+	/// Create an empty set of children.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Return the number of children.
+	pub fn len(&self) -> usize {
+		self.children.len()
+	}
+
+	/// Return true if there are no children.
+	pub fn is_empty(&self) -> bool {
+		self.children.is_empty()
+	}
+
+	/// Iterate over the children and their positions among all children of the
+	/// s-expr.
+	pub fn iter(&self) -> impl Iterator<Item = (Option<usize>, &Value)> {
+		self.children.iter().map(|(index, value)| (*index, value))
+	}
+
+	/// Return the first child with the given name.
+	pub fn get(&self, head: &str) -> Option<&Value> {
+		self.children
+			.iter()
+			.map(|(_, value)| value)
+			.find(|value| value.head() == Some(head))
+	}
+
+	/// Add a child that gets written after all other children of the s-expr.
+	pub fn push(&mut self, value: Value) {
+		self.children.push((None, value));
+	}
+
+	/// Remove all children with the given name.
+	pub fn remove(&mut self, head: &str) {
+		self.children.retain(|(_, value)| value.head() != Some(head));
+	}
+
+	/// Take the children and their positions, and the positions of the children of
+	/// every collection field.
+	#[allow(clippy::type_complexity)]
+	pub(crate) fn into_parts(
+		self
+	) -> (Vec<(Option<usize>, Value)>, Vec<(String, Vec<usize>)>) {
+		(self.children, self.collected)
+	}
+
+	/// Turn the output of [`ValueSerializer`](crate::ser::ValueSerializer) back
+	/// into an `Extra`.
+	pub(crate) fn from_value(value: Value) -> Option<Self> {
+		let Value::List(_, items) = value else {
+			return None;
+		};
+		let mut extra = Self::new();
+		for item in items {
+			let Value::List(_, item) = item else {
+				return None;
+			};
+			let mut item = item.into_iter();
+			match (item.next(), item.next(), item.next()) {
+				(Some(Value::Int(index)), Some(value), None) => {
+					let index = usize::try_from(index).ok()?;
+					extra.children.push((Some(index), value));
+				},
+				(Some(Value::String(name)), Some(Value::List(_, items)), None) => {
+					let positions = items
+						.into_iter()
+						.map(|index| match index {
+							Value::Int(index) => usize::try_from(index).ok(),
+							_ => None
+						})
+						.collect::<Option<_>>()?;
+					extra.collected.push((name, positions));
+				},
+				(Some(value), None, None) => extra.children.push((None, value)),
+				_ => return None
+			}
+		}
+		Some(extra)
+	}
+}
+
+/// Serialize every child as a tuple of its position and value, or only of its
+/// value if it has no position. The positions of the children of a collection
+/// field follow as a tuple of the name of the children and their positions.
+struct Items<'a>(&'a Extra);
+
+impl Serialize for Items<'_> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer
+	{
+		let Extra {
+			children,
+			collected
+		} = self.0;
+		let len = children.len() + collected.len();
+		let mut seq = serializer.serialize_seq(Some(len))?;
+		for (index, value) in children {
+			match index {
+				Some(index) => seq.serialize_element(&(index, value))?,
+				None => seq.serialize_element(&(value,))?
+			}
+		}
+		for item in collected {
+			seq.serialize_element(item)?;
+		}
+		seq.end()
+	}
+}
+
+impl Serialize for Extra {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer
+	{
+		serializer.serialize_newtype_struct(EXTRA, &Items(self))
+	}
+}
+
+/// An item as written by [`Items`].
+enum Item {
+	Child(Option<usize>, Value),
+	Collected(String, Vec<usize>)
+}
+
+struct ItemVisitor;
+
+impl<'de> Visitor<'de> for ItemVisitor {
+	type Value = Item;
+
+	fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str("an unknown child and its position")
+	}
+
+	fn visit_seq<A>(self, mut seq: A) -> Result<Item, A::Error>
+	where
+		A: SeqAccess<'de>
+	{
+		let first = seq
+			.next_element()?
+			.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+		match first {
+			Value::Int(index) => match seq.next_element()? {
+				Some(value) => {
+					let index = usize::try_from(index).map_err(|_| {
+						de::Error::invalid_value(Unexpected::Signed(index), &self)
+					})?;
+					Ok(Item::Child(Some(index), value))
+				},
+				None => Ok(Item::Child(None, Value::Int(index)))
+			},
+			Value::String(name) => match seq.next_element()? {
+				Some(positions) => Ok(Item::Collected(name, positions)),
+				None => Ok(Item::Child(None, Value::String(name)))
+			},
+			value => Ok(Item::Child(None, value))
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for Item {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>
+	{
+		deserializer.deserialize_seq(ItemVisitor)
+	}
+}
+
+struct ExtraVisitor;
+
+impl<'de> Visitor<'de> for ExtraVisitor {
+	type Value = Extra;
+
+	fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str("the unknown children of an s-expr")
+	}
+
+	fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Extra, D::Error>
+	where
+		D: Deserializer<'de>
+	{
+		deserializer.deserialize_seq(self)
+	}
+
+	fn visit_seq<A>(self, mut seq: A) -> Result<Extra, A::Error>
+	where
+		A: SeqAccess<'de>
+	{
+		let mut extra = Extra::new();
+		while let Some(item) = seq.next_element()? {
+			match item {
+				Item::Child(index, value) => extra.children.push((index, value)),
+				Item::Collected(name, positions) => {
+					extra.collected.push((name, positions))
+				}
+			}
+		}
+		Ok(extra)
+	}
+}
+
+impl<'de> Deserialize<'de> for Extra {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>
+	{
+		deserializer.deserialize_newtype_struct(EXTRA, ExtraVisitor)
+	}
+}
@@ -0,0 +1,769 @@
+//! Check if a type can be written in the s-expression format.
+//!
+//! Some mistakes, like a boolean in a tuple struct, are only found once a value of
+//! the offending type is written or read. [`check_layout`] finds them up front by
+//! walking the serde shape of a type, which makes it a good fit for a unit test:
+//!
+//! ```rust
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! #[serde(rename = "pad")]
+//! struct Pad(String, bool);
+//!
+//! let err = serde_kicad_sexpr::check_layout::<Pad>().unwrap_err();
+//! assert_eq!(err.to_string(), "pad > 1: Unnamed boolean");
+//! ```
+
+use crate::{
+	extra::{EXTRA, EXTRA_FIELD},
+	private::{split_tagged, TAGGED},
+	value::VALUE
+};
+use serde::de::{
+	self, value::SeqDeserializer, Deserialize, DeserializeSeed, Deserializer,
+	EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor
+};
+use std::{
+	collections::HashMap,
+	fmt::{self, Display, Formatter}
+};
+use thiserror::Error;
+
+type Result<T, E = IssueKind> = std::result::Result<T, E>;
+
+/// A construct that cannot be written in the s-expression format.
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum IssueKind {
+	#[error("{0}")]
+	Message(String),
+
+	/// A boolean in an unnamed container, i.e. a tuple or a sequence.
+	#[error("Unnamed boolean")]
+	UnnamedBoolean,
+
+	/// A unit in an unnamed container, i.e. a tuple or a sequence.
+	#[error("Unnamed unit")]
+	UnnamedUnit,
+
+	/// A sequence in an unnamed container, i.e. a tuple or a sequence.
+	#[error("Unnamed sequence")]
+	UnnamedSeq,
+
+	/// A self-describing type, like an enum with `#[serde(untagged)]`.
+	#[error("Self-describing types are not supported, use the untagged! macro")]
+	Untyped,
+
+	/// More than one field of a struct has an empty name.
+	#[error("There must only be one field with an empty name")]
+	DuplicateEmptyName,
+
+	/// A field follows the field with an empty name.
+	#[error("Field {0} after the field with an empty name is never read")]
+	FieldAfterEmptyName(&'static str),
+
+	/// The field that keeps the extra children is not the first field.
+	#[error("The $extra field has to be the first field")]
+	MisplacedExtra,
+
+	/// A plain [`Option`] whose presence cannot be told from the next token, either
+	/// because it is in an unnamed container or because a single token follows it.
+	#[error("Cannot tell if the option is present, use serde_kicad_sexpr::Option")]
+	AmbiguousOption,
+
+	#[error("char is unsupported")]
+	Char,
+	#[error("byte array is unsupported")]
+	Bytes,
+	#[error("maps are only supported in named fields")]
+	Map
+}
+
+impl de::Error for IssueKind {
+	fn custom<T: Display>(msg: T) -> Self {
+		Self::Message(msg.to_string())
+	}
+}
+
+/// A construct that cannot be written, and where it was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+	/// The path to the offending value, e.g. `pad > 1`.
+	pub path: String,
+	pub kind: IssueKind
+}
+
+impl Display for Issue {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		if !self.path.is_empty() {
+			write!(f, "{}: ", self.path)?;
+		}
+		Display::fmt(&self.kind, f)
+	}
+}
+
+/// All issues that were found in a type.
+#[derive(Clone, Debug, Error, PartialEq)]
+pub struct Error {
+	pub issues: Vec<Issue>
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		for (i, issue) in self.issues.iter().enumerate() {
+			if i > 0 {
+				f.write_str("\n")?;
+			}
+			Display::fmt(issue, f)?;
+		}
+		Ok(())
+	}
+}
+
+/// Check if `T` can be written in the s-expression format, and return every
+/// construct that cannot.
+///
+/// This walks the serde shape of `T` and all types inside of it, including every
+/// variant of its enums. Types that are not visited by `Deserialize`, like fields
+/// with `#[serde(skip)]`, are not checked. Neither are the values after a leaf that
+/// rejects the placeholder it is given, like an IP address that is parsed from an
+/// empty string.
+pub fn check_layout<'de, T>() -> Result<(), Error>
+where
+	T: Deserialize<'de>
+{
+	let mut state = State::default();
+	loop {
+		state.again = false;
+		let result = T::deserialize(Checker {
+			state: &mut state,
+			ctx: Ctx::Unnamed
+		});
+		if let Err(kind) = result {
+			if !state.rejected {
+				state.issue(kind);
+			}
+		}
+		state.rejected = false;
+		state.path.clear();
+		state.stack.clear();
+		state.cut = false;
+
+		// enums only visit one variant at a time, so keep going until every
+		// variant has been visited
+		if !state.again {
+			break;
+		}
+	}
+
+	match state.issues.is_empty() {
+		true => Ok(()),
+		false => Err(Error {
+			issues: state.issues
+		})
+	}
+}
+
+/// The deepest nesting of structs that is checked.
+const RECURSION_LIMIT: usize = 64;
+
+#[derive(Default)]
+struct State {
+	/// The path to the current value.
+	path: Vec<String>,
+
+	/// The names of the structs that contain the current value.
+	stack: Vec<&'static str>,
+
+	/// Set inside of a recursive type. Options and sequences are empty, and issues
+	/// are not recorded, since they have been recorded by the outer type.
+	cut: bool,
+
+	/// The number of times every enum has been visited, to pick the next variant.
+	enums: HashMap<(&'static str, &'static [&'static str]), usize>,
+
+	/// Set if an enum has a variant that has not been visited.
+	again: bool,
+
+	/// Set if a leaf rejected its placeholder value. The error that this returns
+	/// is not an issue of the layout.
+	rejected: bool,
+
+	/// Set if the current field is written as a single token, like a number or a
+	/// flag, instead of an s-expr.
+	token: bool,
+
+	/// Set if the current field is a plain [`Option`].
+	option: bool,
+
+	issues: Vec<Issue>
+}
+
+impl State {
+	fn issue(&mut self, kind: IssueKind) {
+		let path = self.path.join(" > ");
+		self.issue_at(path, kind);
+	}
+
+	fn issue_at(&mut self, path: String, kind: IssueKind) {
+		if self.cut {
+			return;
+		}
+		let issue = Issue { path, kind };
+		if !self.issues.contains(&issue) {
+			self.issues.push(issue);
+		}
+	}
+
+	/// Pass on the result of visiting a leaf with a placeholder value.
+	fn leaf<T>(&mut self, result: Result<T>) -> Result<T> {
+		if let Err(IssueKind::Message(_)) = result {
+			self.rejected = true;
+		}
+		result
+	}
+
+	/// Start the path with the name of the root s-expr.
+	fn root(&mut self, name: &'static str) {
+		if self.path.is_empty() && self.stack.is_empty() {
+			self.path.push(name.to_owned());
+		}
+	}
+
+	fn check_fields(&mut self, fields: &'static [&'static str]) {
+		if fields.iter().filter(|field| field.is_empty()).count() > 1 {
+			self.issue(IssueKind::DuplicateEmptyName);
+		}
+		if let Some(empty) = fields.iter().position(|field| field.is_empty()) {
+			let later = fields[empty + 1..].iter().filter(|field| !field.is_empty());
+			for field in later {
+				self.issue(IssueKind::FieldAfterEmptyName(field));
+			}
+		}
+		if fields.iter().skip(1).any(|field| *field == EXTRA_FIELD) {
+			self.issue(IssueKind::MisplacedExtra);
+		}
+	}
+
+	/// Visit the content of the struct with the given name.
+	fn enter<F, T>(&mut self, name: &'static str, f: F) -> Result<T>
+	where
+		F: FnOnce(&mut Self) -> Result<T>
+	{
+		if self.stack.len() >= RECURSION_LIMIT {
+			return Err(de::Error::custom("Recursion limit exceeded"));
+		}
+		let cut = self.cut;
+		self.cut |= self.stack.contains(&name);
+		self.stack.push(name);
+		let value = f(self)?;
+		self.stack.pop();
+		self.cut = cut;
+		Ok(value)
+	}
+
+	/// Visit the value at the given path segment.
+	fn segment<F, T>(&mut self, segment: String, f: F) -> Result<T>
+	where
+		F: FnOnce(&mut Self) -> Result<T>
+	{
+		self.path.push(segment);
+		let value = f(self)?;
+		self.path.pop();
+		Ok(value)
+	}
+
+	/// Pick the next variant of an enum.
+	fn variant(
+		&mut self,
+		name: &'static str,
+		variants: &'static [&'static str]
+	) -> &'static str {
+		let candidates = match name {
+			TAGGED => split_tagged(variants).1,
+			_ => variants
+		};
+		if self.cut || candidates.is_empty() {
+			return candidates.first().copied().unwrap_or_default();
+		}
+
+		let visits = self.enums.entry((name, variants)).or_default();
+		let variant = candidates[*visits % candidates.len()];
+		*visits += 1;
+		if *visits < candidates.len() {
+			self.again = true;
+		}
+		variant
+	}
+}
+
+/// The container that a value appears in.
+#[derive(Clone, Copy)]
+enum Ctx {
+	/// A field of a struct. The empty name is the field that holds the remaining
+	/// values of the s-expr.
+	Named(&'static str),
+
+	/// A tuple, a sequence or the root.
+	Unnamed
+}
+
+struct Checker<'a> {
+	state: &'a mut State,
+	ctx: Ctx
+}
+
+impl<'a> Checker<'a> {
+	fn new(state: &'a mut State, ctx: Ctx) -> Self {
+		Self { state, ctx }
+	}
+
+	fn is_named(&self) -> bool {
+		matches!(self.ctx, Ctx::Named(name) if !name.is_empty())
+	}
+
+	fn is_rest(&self) -> bool {
+		matches!(self.ctx, Ctx::Named(""))
+	}
+
+	/// Record that the current field is written as a single token.
+	fn token(&mut self) {
+		if self.is_named() {
+			self.state.token = true;
+		}
+	}
+}
+
+macro_rules! check_number {
+	($($ty:ident: $visit:ident($value:expr)),*) => {
+		paste::paste! {
+			$(
+				fn [<deserialize_ $ty>]<V>(mut self, visitor: V) -> Result<V::Value>
+				where
+					V: Visitor<'de>
+				{
+					self.token();
+					self.state.leaf(visitor.$visit($value))
+				}
+			)*
+		}
+	};
+}
+
+impl<'a, 'de> Deserializer<'de> for Checker<'a> {
+	type Error = IssueKind;
+
+	fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		// the only self-describing type we support is our own Value, which is
+		// checked in deserialize_newtype_struct
+		Err(IssueKind::Untyped)
+	}
+
+	fn deserialize_bool<V>(mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if !self.is_named() {
+			self.state.issue(IssueKind::UnnamedBoolean);
+		}
+		self.token();
+		self.state.leaf(visitor.visit_bool(false))
+	}
+
+	check_number! {
+		i8: visit_u64(1), i16: visit_u64(1), i32: visit_u64(1), i64: visit_u64(1),
+		i128: visit_u64(1), u8: visit_u64(1), u16: visit_u64(1), u32: visit_u64(1),
+		u64: visit_u64(1), u128: visit_u64(1), f32: visit_f64(1.0),
+		f64: visit_f64(1.0)
+	}
+
+	fn deserialize_char<V>(mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.state.issue(IssueKind::Char);
+		self.token();
+		self.state.leaf(visitor.visit_char('_'))
+	}
+
+	fn deserialize_str<V>(mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.token();
+		self.state.leaf(visitor.visit_str(""))
+	}
+
+	fn deserialize_string<V>(mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.token();
+		self.state.leaf(visitor.visit_str(""))
+	}
+
+	fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.state.issue(IssueKind::Bytes);
+		self.state.leaf(visitor.visit_bytes(&[]))
+	}
+
+	fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.deserialize_bytes(visitor)
+	}
+
+	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		// we can only guess if an option is present from the next token, which is
+		// not enough in tuples, or if the option is followed by a single token
+		match self.ctx {
+			Ctx::Unnamed => self.state.issue(IssueKind::AmbiguousOption),
+			Ctx::Named(_) => self.state.option = true
+		}
+		match self.state.cut {
+			true => visitor.visit_none(),
+			false => visitor.visit_some(self)
+		}
+	}
+
+	fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if !self.is_named() {
+			self.state.issue(IssueKind::UnnamedUnit);
+		}
+		visitor.visit_unit()
+	}
+
+	fn deserialize_unit_struct<V>(
+		self,
+		_name: &'static str,
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_unit()
+	}
+
+	fn deserialize_newtype_struct<V>(
+		mut self,
+		name: &'static str,
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if name == EXTRA {
+			return visitor.visit_seq(SeqDeserializer::new(std::iter::empty::<()>()));
+		}
+		if name == VALUE {
+			self.token();
+			return self.state.leaf(visitor.visit_str(""));
+		}
+		self.state.root(name);
+		self.state.enter(name, |state| {
+			visitor.visit_newtype_struct(Checker::new(state, Ctx::Unnamed))
+		})
+	}
+
+	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if !self.is_named() && !self.is_rest() {
+			self.state.issue(IssueKind::UnnamedSeq);
+		}
+		let len = match self.state.cut {
+			true => 0,
+			false => 1
+		};
+		visitor.visit_seq(Elements::new(self.state, len))
+	}
+
+	fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if !self.is_named() && !self.is_rest() {
+			self.state.issue(IssueKind::UnnamedSeq);
+		}
+		visitor.visit_seq(Elements::new(self.state, len))
+	}
+
+	fn deserialize_tuple_struct<V>(
+		self,
+		name: &'static str,
+		len: usize,
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.state.root(name);
+		self.state
+			.enter(name, |state| visitor.visit_seq(Elements::new(state, len)))
+	}
+
+	fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if !self.is_named() {
+			self.state.issue(IssueKind::Map);
+		}
+		let done = self.state.cut;
+		visitor.visit_map(Entry {
+			state: self.state,
+			done
+		})
+	}
+
+	fn deserialize_struct<V>(
+		self,
+		name: &'static str,
+		fields: &'static [&'static str],
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.state.root(name);
+		self.state.check_fields(fields);
+		self.state
+			.enter(name, |state| visitor.visit_seq(Fields::new(state, fields)))
+	}
+
+	fn deserialize_enum<V>(
+		self,
+		name: &'static str,
+		variants: &'static [&'static str],
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		let variant = self.state.variant(name, variants);
+		visitor.visit_enum(Enum {
+			state: self.state,
+			variant
+		})
+	}
+
+	fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.state.leaf(visitor.visit_str(""))
+	}
+
+	fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_unit()
+	}
+}
+
+/// The fields of a struct, in order.
+struct Fields<'a> {
+	state: &'a mut State,
+	fields: &'static [&'static str],
+	index: usize,
+
+	/// The paths of the plain options that have not been followed by a single
+	/// token yet.
+	options: Vec<String>
+}
+
+impl<'a> Fields<'a> {
+	fn new(state: &'a mut State, fields: &'static [&'static str]) -> Self {
+		Self {
+			state,
+			fields,
+			index: 0,
+			options: Vec::new()
+		}
+	}
+}
+
+impl<'a, 'de> SeqAccess<'de> for Fields<'a> {
+	type Error = IssueKind;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		let Some(&field) = self.fields.get(self.index) else {
+			return Ok(None);
+		};
+		let segment = match field {
+			"" => self.index.to_string(),
+			field => field.to_owned()
+		};
+		self.index += 1;
+
+		// keep the flags of the surrounding field while visiting this one
+		let token = std::mem::replace(&mut self.state.token, false);
+		let option = std::mem::replace(&mut self.state.option, false);
+		let options = &mut self.options;
+		let value = self.state.segment(segment, |state| {
+			let value = seed.deserialize(Checker::new(state, Ctx::Named(field)))?;
+
+			// an option that is followed by a single token takes that token
+			if state.token {
+				for path in options.drain(..) {
+					state.issue_at(path, IssueKind::AmbiguousOption);
+				}
+			}
+			if state.option {
+				options.push(state.path.join(" > "));
+			}
+			Ok(value)
+		})?;
+		self.state.token = token;
+		self.state.option = option;
+		Ok(Some(value))
+	}
+}
+
+/// The elements of a tuple or a sequence.
+struct Elements<'a> {
+	state: &'a mut State,
+	len: usize,
+	index: usize
+}
+
+impl<'a> Elements<'a> {
+	fn new(state: &'a mut State, len: usize) -> Self {
+		Self {
+			state,
+			len,
+			index: 0
+		}
+	}
+}
+
+impl<'a, 'de> SeqAccess<'de> for Elements<'a> {
+	type Error = IssueKind;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		if self.index >= self.len {
+			return Ok(None);
+		}
+		let segment = self.index.to_string();
+		self.index += 1;
+		self.state
+			.segment(segment, |state| {
+				seed.deserialize(Checker::new(state, Ctx::Unnamed))
+			})
+			.map(Some)
+	}
+}
+
+/// A map with a single entry.
+struct Entry<'a> {
+	state: &'a mut State,
+	done: bool
+}
+
+impl<'a, 'de> MapAccess<'de> for Entry<'a> {
+	type Error = IssueKind;
+
+	fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+	where
+		K: DeserializeSeed<'de>
+	{
+		if self.done {
+			return Ok(None);
+		}
+		self.done = true;
+		self.state
+			.segment("key".to_owned(), |state| {
+				seed.deserialize(Checker::new(state, Ctx::Unnamed))
+			})
+			.map(Some)
+	}
+
+	fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+	where
+		V: DeserializeSeed<'de>
+	{
+		self.state.segment("value".to_owned(), |state| {
+			seed.deserialize(Checker::new(state, Ctx::Unnamed))
+		})
+	}
+}
+
+/// An enum with one variant picked by [`State::variant`].
+struct Enum<'a> {
+	state: &'a mut State,
+	variant: &'static str
+}
+
+impl<'a, 'de> EnumAccess<'de> for Enum<'a> {
+	type Error = IssueKind;
+	type Variant = Self;
+
+	fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+	where
+		V: DeserializeSeed<'de>
+	{
+		let value = seed.deserialize(self.variant.into_deserializer())?;
+		Ok((value, self))
+	}
+}
+
+impl<'a, 'de> VariantAccess<'de> for Enum<'a> {
+	type Error = IssueKind;
+
+	fn unit_variant(self) -> Result<()> {
+		Ok(())
+	}
+
+	fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+	where
+		T: DeserializeSeed<'de>
+	{
+		self.state.segment(self.variant.to_owned(), |state| {
+			seed.deserialize(Checker::new(state, Ctx::Unnamed))
+		})
+	}
+
+	fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.state.segment(self.variant.to_owned(), |state| {
+			visitor.visit_seq(Elements::new(state, len))
+		})
+	}
+
+	fn struct_variant<V>(
+		self,
+		fields: &'static [&'static str],
+		visitor: V
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.state.segment(self.variant.to_owned(), |state| {
+			state.check_fields(fields);
+			visitor.visit_seq(Fields::new(state, fields))
+		})
+	}
+}
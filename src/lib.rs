@@ -22,9 +22,10 @@
 //!    a tuple or a sequence. These fields cannot appear in unnamed containers
 //!    (i.e. tuple structs).
 //!
-//!  - Deserializing `Option` is not supported, because we need to know the type inside
-//!    the option to determine if it is present or missing. To deserialize optional
-//!    values, use the custom deserializing logic from this crate:
+//!  - Since we don't know the type inside an `Option`, we need to guess if it is
+//!    present or missing from the next token. The value is missing if the s-expr
+//!    ends, or if the next token is an s-expr that is named like a later field or
+//!    there is a later field with an empty name (see below):
 //!
 //!    ```rust
 //!    # use serde::{Deserialize, Serialize};
@@ -32,11 +33,15 @@
 //!    struct Position {
 //!        x: i32,
 //!        y: i32,
-//!        #[serde(with = "serde_kicad_sexpr::Option")]
 //!        rotation: Option<i32>
 //!    }
 //!    ```
 //!
+//!    This guess fails in tuple structs, and if the option is followed by a field
+//!    that is written as a single token, like a number or a flag. Use
+//!    `#[serde(with = "serde_kicad_sexpr::Option")]` for these options, which tries
+//!    to read the value instead. [`check_layout`] reports the options that need it.
+//!
 //!  - If you need to deserialize some sort of container with an unknown number of
 //!    children, use a special field with an empty name, like so:
 //!
@@ -56,6 +61,59 @@
 //!    fields after a field with an empty name, and there must only be one field
 //!    with an empty name.
 //!
+//!  - If children of different types are interleaved, like the lines and pads of
+//!    a footprint, collect each of them in a field whose name is the name of the
+//!    children followed by a `*`:
+//!
+//!    ```rust
+//!    # use serde::{Deserialize, Serialize};
+//!    # #[derive(Deserialize, Serialize)]
+//!    # #[serde(rename = "fp_line")]
+//!    # struct Line;
+//!    # #[derive(Deserialize, Serialize)]
+//!    # #[serde(rename = "pad")]
+//!    # struct Pad;
+//!    #[derive(Deserialize, Serialize)]
+//!    struct Footprint {
+//!        #[serde(default, rename = "fp_line*")]
+//!        lines: Vec<Line>,
+//!        #[serde(default, rename = "pad*")]
+//!        pads: Vec<Pad>
+//!    }
+//!    ```
+//!
+//!    These fields can appear anywhere in the struct, and there can be any number
+//!    of them. The children are written in the order of the fields, unless the
+//!    struct has an [`Extra`] field, which records their positions so they are
+//!    written back in their original order. Alternatively, use a field with an
+//!    empty name whose elements are an [`untagged!`] enum.
+//!
+//!  - Maps are written as one s-expr per entry, named like the map's field. The
+//!    key is the first value of the s-expr and the value follows it. If the value
+//!    is a struct or a tuple, its content is written right after the key:
+//!
+//!    ```rust
+//!    # use serde::{Deserialize, Serialize};
+//!    # use std::collections::BTreeMap;
+//!    #[derive(Deserialize, Serialize)]
+//!    struct Nets {
+//!        #[serde(rename = "net")]
+//!        nets: BTreeMap<u32, String>
+//!    }
+//!    ```
+//!
+//!    For example, two nets are written as `(Nets (net 0 "") (net 1 GND))`.
+//!
+//!  - S-exprs that are not modelled by your types are an error by default. To read
+//!    files that were written by newer versions of KiCad, either skip them with
+//!    [`Deserializer::lenient`], or keep them in an [`Extra`] field so that they
+//!    are written back unchanged.
+//!
+//!  - Unit enum variants are written as symbols. Tuple and struct variants are
+//!    written as s-exprs named like the variant, e.g. `(line (xy 0 0) (xy 1 1))`
+//!    for `Shape::Line(Point, Point)`. Newtype variants are written like their
+//!    content, which has to be named like the variant.
+//!
 //!  - Untagged enums are not supported. If you need to parse one from a number of
 //!    types, use the [`untagged!`] macro:
 //!
@@ -69,19 +127,59 @@
 //!    }
 //!    ```
 //!
+//!    If the variant is selected by a value inside of the s-expr instead of its
+//!    name, use the [`tagged!`] macro.
+//!
+//!  - Floats are rounded to six decimals like KiCad does, so `0.1 + 0.2` is written
+//!    as `0.3`. Earlier versions wrote the shortest representation of every float;
+//!    use [`FloatFormat::shortest`] to keep doing so. The floats of a [`Value`] are
+//!    always written with all of their digits.
+//!
+//!  - With the `derive` feature, you can use `#[derive(SExpr)]` instead of the
+//!    serde derives. It describes the special cases above with `#[sexpr(..)]`
+//!    attributes, and rejects layouts that cannot be written at compile time.
+//!
+//! To find types that break these rules in a unit test instead of at runtime, use
+//! [`check_layout`].
+//!
 //!  [`Serializer`]: serde::ser::Serializer
 //!  [`Deserializer`]: serde::de::Deserializer
 //!  [`untagged!`]: serde_kicad_sexpr::untagged
+//!  [`tagged!`]: crate::tagged
+//!  [`Deserializer::lenient`]: crate::de::Deserializer::lenient
+//!  [`Extra`]: crate::Extra
+//!  [`check_layout`]: crate::check_layout
+//!  [`FloatFormat::shortest`]: crate::ser::FloatFormat::shortest
+//!  [`Value`]: crate::Value
+
+// the tagged! macro names this crate in the serde attributes of the code that it
+// generates, which has to work in the tests of this crate as well
+#[cfg(test)]
+extern crate self as serde_kicad_sexpr;
 
+mod extra;
 mod option;
-#[macro_use]
+mod tagged;
 mod untagged;
+mod value;
 
+pub mod cst;
 pub mod de;
+pub mod layout;
 #[doc(hidden)]
 pub mod private;
 pub mod ser;
 
-pub use de::from_str;
+pub use de::{from_reader, from_slice, from_str};
+pub use extra::Extra;
+pub use layout::check_layout;
 pub use option::{deserialize_option, OptionDef as Option};
-pub use ser::{to_string, to_string_pretty};
+#[cfg(feature = "derive")]
+pub use serde_kicad_sexpr_derive::SExpr;
+pub use ser::{
+	to_fmt_writer, to_fmt_writer_kicad, to_fmt_writer_pretty, to_string,
+	to_string_kicad, to_string_pretty, to_string_with_config, to_vec, to_vec_kicad,
+	to_vec_pretty, to_writer, to_writer_kicad, to_writer_pretty,
+	to_writer_with_config
+};
+pub use value::Value;
@@ -11,6 +11,11 @@ use std::{
 
 /// Deserialize an [`Option`] in a way that is supported by the s-expression format.
 ///
+/// Plain [`Option`] fields are supported by the deserializer, which guesses if the
+/// value is present from the next token. Use this where that guess fails: In tuple
+/// structs, in front of a field that is written as a single token (e.g. `(n x)` for
+/// two optional values), or if the value is an s-expr named like a later field.
+///
 /// ### Example
 ///
 /// ```rust
@@ -29,17 +34,17 @@ use std::{
 /// #[derive(Deserialize, Serialize)]
 /// #[serde(deny_unknown_fields, rename = "font")]
 /// struct Font {
-/// 	size: Size,
+///     size: Size,
 ///
-/// 	// This attribute enables our custom deserialize logic.
-/// 	#[serde(with = "serde_kicad_sexpr::Option")]
-/// 	thickness: Option<Thickness>,
+///     // This attribute enables our custom deserialize logic.
+///     #[serde(with = "serde_kicad_sexpr::Option")]
+///     thickness: Option<Thickness>,
 ///
-/// 	bold: bool
+///     bold: bool
 /// }
 /// # assert_eq!(
-/// # 	serde_kicad_sexpr::from_str::<Font>("(font (size 1 1) bold)").unwrap(),
-/// # 	Font { size: Size(1.0, 1.0), thickness: None, bold: true }
+/// #     serde_kicad_sexpr::from_str::<Font>("(font (size 1 1) bold)").unwrap(),
+/// #     Font { size: Size(1.0, 1.0), thickness: None, bold: true }
 /// # );
 /// ```
 ///
@@ -92,17 +97,17 @@ where
 /// #[derive(Deserialize, Serialize)]
 /// #[serde(deny_unknown_fields, rename = "font")]
 /// struct Font {
-/// 	size: Size,
+///     size: Size,
 ///
-/// 	// This attribute enables our custom deserialize logic.
-/// 	#[serde(deserialize_with = "serde_kicad_sexpr::deserialize_option")]
-/// 	thickness: Option<Thickness>,
+///     // This attribute enables our custom deserialize logic.
+///     #[serde(deserialize_with = "serde_kicad_sexpr::deserialize_option")]
+///     thickness: Option<Thickness>,
 ///
-/// 	bold: bool
+///     bold: bool
 /// }
 /// # assert_eq!(
-/// # 	serde_kicad_sexpr::from_str::<Font>("(font (size 1 1) bold)").unwrap(),
-/// # 	Font { size: Size(1.0, 1.0), thickness: None, bold: true }
+/// #     serde_kicad_sexpr::from_str::<Font>("(font (size 1 1) bold)").unwrap(),
+/// #     Font { size: Size(1.0, 1.0), thickness: None, bold: true }
 /// # );
 /// ```
 ///
@@ -1,16 +1,102 @@
+use crate::value::VALUE;
 use serde::{
 	de::{self, Deserializer, Visitor},
 	forward_to_deserialize_any
 };
 use std::{
+	any::TypeId,
+	collections::HashMap,
 	error::Error,
-	fmt::{self, Debug, Display, Formatter}
+	fmt::{self, Debug, Display, Formatter},
+	sync::{PoisonError, RwLock}
 };
 
 pub use once_cell::sync::Lazy as SyncLazy;
+pub use serde;
+
+/// The magic enum name used by the [`tagged!`](crate::tagged) macro. The first
+/// variant is the comma-separated path to the tag. It is followed by the values of
+/// the tag, and then by the names of the s-exprs of the variants in the same order.
+/// Names that are unknown are replaced by this magic name.
+pub const TAGGED: &str = "$serde_kicad_sexpr::private::Tagged";
+
+/// Split the variants of a [`tagged!`](crate::tagged) enum into the path to the
+/// tag, the values of the tag and the names of the s-exprs.
+pub fn split_tagged<'a>(
+	variants: &'a [&'static str]
+) -> (&'static str, &'a [&'static str], &'a [&'static str]) {
+	let (values, names) = variants[1..].split_at(variants.len() / 2);
+	(variants[0], values, names)
+}
+
+/// Return the name of the s-expr of a [`tagged!`](crate::tagged) variant that
+/// wraps `T`, or [`TAGGED`] if it is unknown.
+pub fn tagged_name<'de, T>() -> &'static str
+where
+	T: de::Deserialize<'de>
+{
+	variant_name::<T>().unwrap_or(TAGGED)
+}
+
+/// The variant names used by the [`untagged!`](crate::untagged) macro for variants
+/// that wrap a string, an integer or a float rather than an s-expr.
+pub const UNTAGGED_STRING: &str = "$serde_kicad_sexpr::private::String";
+pub const UNTAGGED_INT: &str = "$serde_kicad_sexpr::private::Int";
+pub const UNTAGGED_FLOAT: &str = "$serde_kicad_sexpr::private::Float";
+
+/// Return the variant name of an untagged enum variant that wraps `T`. This is
+/// the name of the s-expr for structs, or one of the magic names above for
+/// primitives.
+pub fn variant_name<'de, T>() -> Result<&'static str, String>
+where
+	T: de::Deserialize<'de>
+{
+	match T::deserialize(NameExtractor) {
+		Ok(_) => unreachable!(),
+		Err(Extraction::Ok(name)) => Ok(name),
+		Err(Extraction::Err(err)) => Err(err)
+	}
+}
+
+type Variants = Result<&'static [&'static str], String>;
+
+/// Return the variant names of the generic untagged enum `T`. They are computed
+/// and leaked only once for every instance of the enum.
+pub fn generic_variants<T, F>(compute: F) -> Variants
+where
+	T: 'static,
+	F: FnOnce() -> Result<Vec<&'static str>, String>
+{
+	static VARIANTS: SyncLazy<RwLock<HashMap<TypeId, Variants>>> =
+		SyncLazy::new(Default::default);
+
+	let key = TypeId::of::<T>();
+	let variants = VARIANTS.read().unwrap_or_else(PoisonError::into_inner);
+	if let Some(names) = variants.get(&key) {
+		return names.clone();
+	}
+	drop(variants);
+
+	let names = compute().map(|names| &*Box::leak(names.into_boxed_slice()));
+	let mut variants = VARIANTS.write().unwrap_or_else(PoisonError::into_inner);
+	variants.entry(key).or_insert(names).clone()
+}
 
 pub struct NameExtractor;
 
+macro_rules! extract_primitive {
+	($($name:ident => $($method:ident)+);+) => {
+		$($(
+			fn $method<V>(self, _visitor: V) -> Result<V::Value, Extraction>
+			where
+				V: Visitor<'de>
+			{
+				Err(Extraction::Ok($name))
+			}
+		)+)+
+	};
+}
+
 #[derive(Debug)]
 pub enum Extraction {
 	Ok(&'static str),
@@ -60,11 +146,15 @@ impl<'de> Deserializer<'de> for NameExtractor {
 	fn deserialize_newtype_struct<V>(
 		self,
 		name: &'static str,
-		_visitor: V
+		visitor: V
 	) -> Result<V::Value, Extraction>
 	where
 		V: Visitor<'de>
 	{
+		// a value has no name
+		if name == VALUE {
+			return self.deserialize_any(visitor);
+		}
 		Err(Extraction::Ok(name))
 	}
 
@@ -92,8 +182,15 @@ impl<'de> Deserializer<'de> for NameExtractor {
 		Err(Extraction::Ok(name))
 	}
 
+	extract_primitive! {
+		UNTAGGED_STRING => deserialize_char deserialize_str deserialize_string;
+		UNTAGGED_INT => deserialize_i8 deserialize_i16 deserialize_i32
+			deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16
+			deserialize_u32 deserialize_u64 deserialize_u128;
+		UNTAGGED_FLOAT => deserialize_f32 deserialize_f64
+	}
+
 	forward_to_deserialize_any! {
-		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
-		byte_buf option unit seq tuple map enum identifier ignored_any
+		bool bytes byte_buf option unit seq tuple map enum identifier ignored_any
 	}
 }
@@ -1,8 +1,12 @@
 use serde::ser;
-use std::fmt::Display;
+use std::{
+	fmt::{self, Display},
+	io, mem,
+	sync::Arc
+};
 use thiserror::Error;
 
-#[derive(Clone, Debug, Error, PartialEq)]
+#[derive(Clone, Debug, Error)]
 pub enum Error {
 	#[error("{0}")]
 	Message(String),
@@ -27,14 +31,59 @@ pub enum Error {
 	#[error("Unnamed sequence")]
 	UnnamedSeq,
 
+	/// This error will be returned if a symbol or the name of an untyped s-expr
+	/// was expected, but some other value was found.
+	#[error("Expected string")]
+	ExpectedString,
+
+	/// This error will be returned if the extra children of an s-expr contain
+	/// something other than untyped values.
+	#[error("Invalid extra children")]
+	InvalidExtra,
+
+	/// This error will be returned if a float is NaN or infinite, and the
+	/// serializer only accepts finite floats.
+	#[error("Non-finite float")]
+	NonFiniteFloat,
+
 	#[error("char is unsupported")]
 	Char,
 	#[error("byte array is unsupported")]
 	Bytes,
+
+	/// This error used to be returned for enum variants with fields. Tuple and
+	/// struct variants are supported now, so it is no longer returned.
+	#[deprecated(note = "struct and tuple variants are now supported")]
 	#[error("enums with non-unit variants are not supported")]
 	ComplexEnum,
-	#[error("maps are not supported")]
-	Map
+
+	#[error("maps are only supported in named fields")]
+	Map,
+
+	/// This error will be returned if writing to an [`io::Write`] failed.
+	///
+	///  [`io::Write`]: std::io::Write
+	#[error("I/O error: {0}")]
+	Io(#[source] Arc<io::Error>),
+
+	/// This error will be returned if writing to a [`fmt::Write`] failed.
+	///
+	///  [`fmt::Write`]: std::fmt::Write
+	#[error("Formatting error")]
+	Fmt(#[source] fmt::Error)
+}
+
+impl PartialEq for Error {
+	fn eq(&self, other: &Self) -> bool {
+		// variants with data have to be listed here, all others are equal if they
+		// are the same variant
+		match (self, other) {
+			(Self::Message(a), Self::Message(b)) => a == b,
+			// I/O errors can't be compared, so only their kinds are
+			(Self::Io(a), Self::Io(b)) => a.kind() == b.kind(),
+			_ => mem::discriminant(self) == mem::discriminant(other)
+		}
+	}
 }
 
 impl ser::Error for Error {
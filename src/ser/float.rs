@@ -0,0 +1,137 @@
+use std::fmt::Display;
+
+/// Options for writing floats.
+///
+/// By default, floats are rounded to six decimals like KiCad's
+/// `FormatInternalUnits` does, which is the nanometre resolution of board files.
+/// Values that went through some arithmetic, like `0.1 + 0.2`, would otherwise be
+/// written as `0.30000000000000004`. Floats are never written in scientific
+/// notation. The floats of a [`Value`](crate::Value) are not affected by this, and
+/// are always written with all of their digits.
+///
+/// ### Example
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_kicad_sexpr::ser::{FloatFormat, Serializer};
+///
+/// #[derive(Serialize)]
+/// #[serde(rename = "at")]
+/// struct Position(f64, f64);
+///
+/// let position = Position(0.1 + 0.2, 2.54 * 3.0);
+/// let written = serde_kicad_sexpr::to_string(&position).unwrap();
+/// assert_eq!(written, "(at 0.3 7.62)");
+///
+/// let float_format = FloatFormat::shortest();
+/// let mut ser = Serializer::new(String::new()).float_format(float_format);
+/// position.serialize(&mut ser).unwrap();
+/// assert_eq!(ser.into_inner(), "(at 0.30000000000000004 7.62)");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct FloatFormat {
+	pub(super) max_decimals: Option<usize>,
+	pub(super) trim_zeros: bool
+}
+
+impl FloatFormat {
+	/// The default options, which round to the nanometre resolution of board files
+	/// like KiCad's `FormatInternalUnits` does.
+	pub fn kicad() -> Self {
+		Self {
+			max_decimals: Some(6),
+			trim_zeros: true
+		}
+	}
+
+	/// Write the shortest representation that reads back as the same value.
+	pub fn shortest() -> Self {
+		Self {
+			max_decimals: None,
+			trim_zeros: true
+		}
+	}
+
+	/// Round floats to at most this many decimals, or `None` to keep all of them.
+	pub fn max_decimals(mut self, max_decimals: Option<usize>) -> Self {
+		self.max_decimals = max_decimals;
+		self
+	}
+
+	/// Remove zeros at the end of the decimals, and the decimal point if no
+	/// decimals are left. If this is not set, floats are padded with zeros to
+	/// [`max_decimals`](Self::max_decimals).
+	pub fn trim_zeros(mut self, trim_zeros: bool) -> Self {
+		self.trim_zeros = trim_zeros;
+		self
+	}
+
+	/// Format a float. Rust never writes floats in scientific notation.
+	pub(crate) fn format<F: Float>(&self, value: F) -> String {
+		let shortest = value.to_string();
+		let Some(max_decimals) = self.max_decimals else {
+			return shortest;
+		};
+		if !value.is_finite() {
+			return shortest;
+		}
+
+		// Only round if the shortest representation has too many decimals. The
+		// exact value of an `f32` has more decimals than were written, which would
+		// show up as binary noise otherwise.
+		let decimals = shortest.split_once('.').map_or(0, |(_, d)| d.len());
+		let mut formatted = match decimals > max_decimals {
+			true => format!("{value:.max_decimals$}"),
+			false => shortest
+		};
+		if !self.trim_zeros && decimals < max_decimals {
+			if decimals == 0 {
+				formatted.push('.');
+			}
+			formatted.push_str(&"0".repeat(max_decimals - decimals));
+		}
+		if self.trim_zeros && formatted.contains('.') {
+			let len = formatted.trim_end_matches('0').trim_end_matches('.').len();
+			formatted.truncate(len);
+		}
+		// a small negative value can be rounded to zero
+		let zero = formatted.bytes().all(|b| b"-0.".contains(&b));
+		if zero && formatted.starts_with('-') {
+			formatted.remove(0);
+		}
+		formatted
+	}
+}
+
+impl Default for FloatFormat {
+	fn default() -> Self {
+		Self::kicad()
+	}
+}
+
+/// Format a float with all of its digits and a decimal point, so that it reads back
+/// as the same float and not as an integer.
+pub(crate) fn format_exact(value: f64) -> String {
+	let mut formatted = value.to_string();
+	if value.is_finite() && !formatted.contains('.') {
+		formatted.push_str(".0");
+	}
+	formatted
+}
+
+/// A float type that can be written with a [`FloatFormat`].
+pub(crate) trait Float: Copy + Display {
+	fn is_finite(self) -> bool;
+}
+
+impl Float for f32 {
+	fn is_finite(self) -> bool {
+		f32::is_finite(self)
+	}
+}
+
+impl Float for f64 {
+	fn is_finite(self) -> bool {
+		f64::is_finite(self)
+	}
+}
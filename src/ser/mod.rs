@@ -1,19 +1,65 @@
 use itoa::Integer;
 use paste::paste;
 use serde::ser::{
-	self, Serialize, SerializeSeq, SerializeStruct, SerializeTuple,
-	SerializeTupleStruct
+	self, Serialize, SerializeMap, SerializeSeq, SerializeStruct,
+	SerializeStructVariant, SerializeTuple, SerializeTupleStruct,
+	SerializeTupleVariant
 };
+use crate::{
+	de::collection_name,
+	extra::{Extra, EXTRA},
+	value::{EXACT_FLOAT, LIST, STRING, SYMBOL},
+	Value
+};
+use std::{fmt, io, sync::Arc};
 
 mod error;
+mod float;
+mod pretty;
 pub use error::Error;
+pub(crate) use float::{format_exact, Float};
+pub use float::FloatFormat;
+pub use pretty::PrettyConfig;
+
+/// The output of a [`Serializer`]. This is implemented for all types that
+/// implement [`fmt::Write`], like [`String`]. To write to an [`io::Write`],
+/// wrap it in an [`IoWrite`].
+pub trait Write {
+	fn write_str(&mut self, s: &str) -> Result<()>;
+}
+
+impl<W> Write for W
+where
+	W: ?Sized + fmt::Write
+{
+	fn write_str(&mut self, s: &str) -> Result<()> {
+		fmt::Write::write_str(self, s).map_err(Error::Fmt)
+	}
+}
+
+/// An adapter that implements [`Write`] for any [`io::Write`].
+///
+/// The serializer performs lots of small writes, so you might want to use a
+/// [`BufWriter`](io::BufWriter) when writing to a file or socket.
+pub struct IoWrite<W>(pub W);
 
-pub struct Serializer {
-	/// Buffer that the output gets written to.
-	buf: String,
+impl<W> Write for IoWrite<W>
+where
+	W: io::Write
+{
+	fn write_str(&mut self, s: &str) -> Result<()> {
+		self.0
+			.write_all(s.as_bytes())
+			.map_err(|err| Error::Io(Arc::new(err)))
+	}
+}
 
-	/// Set to true for pretty output.
-	pretty: bool,
+pub struct Serializer<W = String> {
+	/// The output that gets written to.
+	out: Output<W>,
+
+	/// The options for pretty output, or `None` for compact output.
+	config: Option<PrettyConfig>,
 
 	/// The current level of nesting
 	lvl: usize,
@@ -21,20 +67,178 @@ pub struct Serializer {
 	/// The indentation (in levels) of the current line
 	indent: usize,
 
+	/// The column (in bytes) of the current line
+	column: usize,
+
+	/// Set to true if the last thing written was the end of an s-expr
+	after_sexpr: bool,
+
+	/// Set to true if tokens of the current s-expr were wrapped onto a new line
+	multiline: bool,
+
+	/// Set to true if the last s-expr that was started is part of a run
+	in_run: bool,
+
+	/// The level of nesting of the outermost always-inline s-expr
+	inline_lvl: Option<usize>,
+
+	/// Set to true if the next field is the name of an untyped s-expr
+	head_pending: bool,
+
+	/// The extra children of the s-exprs that are currently being written
+	extra: Vec<PendingExtra>,
+
+	/// Set to true to reject floats that are NaN or infinite
+	strict_floats: bool,
+
+	/// The options for writing floats
+	float_format: FloatFormat,
+
 	/// An itoa::Buffer to re-use when printing integers
 	itoa_buffer: itoa::Buffer
 }
 
-impl Serializer {
-	fn new(pretty: bool) -> Self {
+impl<W: Write> Serializer<W> {
+	fn with_config(
+		out: W,
+		config: Option<PrettyConfig>,
+		float_format: FloatFormat
+	) -> Self {
 		Self {
-			buf: String::new(),
-			pretty,
+			out: Output {
+				out,
+				buffers: Vec::new()
+			},
+			config,
 			lvl: 0,
 			indent: 0,
+			column: 0,
+			after_sexpr: false,
+			multiline: false,
+			in_run: false,
+			inline_lvl: None,
+			head_pending: false,
+			extra: Vec::new(),
+			strict_floats: false,
+			float_format,
 			itoa_buffer: itoa::Buffer::new()
 		}
 	}
+
+	/// Create a serializer that writes compact output to `out`.
+	pub fn new(out: W) -> Self {
+		Self::with_config(out, None, FloatFormat::default())
+	}
+
+	/// Create a serializer that writes pretty output to `out`.
+	pub fn pretty(out: W) -> Self {
+		Self::pretty_with_config(out, PrettyConfig::new())
+	}
+
+	/// Create a serializer that writes the same output as KiCad to `out`. See
+	/// [`to_string_kicad`] for details.
+	pub fn kicad(out: W) -> Self {
+		Self::with_config(out, Some(PrettyConfig::kicad()), FloatFormat::kicad())
+	}
+
+	/// Create a serializer that writes pretty output to `out`, using the options
+	/// from `config`.
+	pub fn pretty_with_config(out: W, config: PrettyConfig) -> Self {
+		Self::with_config(out, Some(config), FloatFormat::default())
+	}
+
+	/// Write floats using the options from `float_format`.
+	pub fn float_format(mut self, float_format: FloatFormat) -> Self {
+		self.float_format = float_format;
+		self
+	}
+
+	/// Fail with [`Error::NonFiniteFloat`] instead of writing floats that are NaN
+	/// or infinite. KiCad cannot read these values back.
+	///
+	/// ```rust
+	/// use serde::Serialize;
+	/// use serde_kicad_sexpr::ser::{Error, Serializer};
+	///
+	/// #[derive(Serialize)]
+	/// #[serde(rename = "width")]
+	/// struct Width(f32);
+	///
+	/// let mut ser = Serializer::new(String::new()).strict_floats(true);
+	/// let err = Width(f32::NAN).serialize(&mut ser).unwrap_err();
+	/// assert_eq!(err, Error::NonFiniteFloat);
+	/// ```
+	pub fn strict_floats(mut self, strict_floats: bool) -> Self {
+		self.strict_floats = strict_floats;
+		self
+	}
+
+	/// Return the output of this serializer.
+	pub fn into_inner(self) -> W {
+		self.out.out
+	}
+}
+
+/// The output of a [`Serializer`], which writes to a buffer instead while a child
+/// is written that has to be moved to its original position.
+struct Output<W> {
+	out: W,
+
+	/// The buffers of the children that are being written, the innermost last
+	buffers: Vec<String>
+}
+
+impl<W: Write> Output<W> {
+	fn write_str(&mut self, s: &str) -> Result<()> {
+		match self.buffers.last_mut() {
+			Some(buffer) => {
+				buffer.push_str(s);
+				Ok(())
+			},
+			None => self.out.write_str(s)
+		}
+	}
+}
+
+/// The state of a [`Serializer`] that decides how the next token is written.
+#[derive(Clone, Copy)]
+struct Cursor {
+	indent: usize,
+	column: usize,
+	after_sexpr: bool,
+	multiline: bool,
+	in_run: bool
+}
+
+/// The extra children of an s-expr that still need to be written.
+struct PendingExtra {
+	/// The level of nesting inside the s-expr
+	lvl: usize,
+
+	/// The number of children written so far
+	written: usize,
+
+	/// The children and their positions, the next one last
+	items: Vec<(Option<usize>, Pending)>,
+
+	/// The original positions of the children of every collection field
+	collected: Vec<(String, Vec<usize>)>
+}
+
+/// A child that is written once the children in front of it were written.
+enum Pending {
+	/// An unknown child.
+	Value(Value),
+
+	/// A child of a collection field that was already written to a buffer, and
+	/// the state of the serializer after it.
+	Written(String, Cursor)
+}
+
+/// The order in which pending children are written. Children without a position
+/// come last.
+fn pending_order(index: Option<usize>) -> (bool, Option<usize>) {
+	(index.is_none(), index)
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -43,75 +247,415 @@ pub fn to_string<T>(value: &T) -> Result<String>
 where
 	T: ?Sized + Serialize
 {
-	let mut serializer = Serializer::new(false);
+	let mut serializer = Serializer::new(String::new());
 	value.serialize(&mut serializer)?;
-	Ok(serializer.buf)
+	Ok(serializer.into_inner())
 }
 
 pub fn to_string_pretty<T>(value: &T) -> Result<String>
 where
 	T: ?Sized + Serialize
 {
-	let mut serializer = Serializer::new(true);
+	let mut serializer = Serializer::pretty(String::new());
+	value.serialize(&mut serializer)?;
+	Ok(serializer.into_inner())
+}
+
+/// Serialize `value` the same way KiCad formats its files.
+///
+/// This uses tab indentation and puts every nested s-expr on its own line, except
+/// for consecutive `(xy ..)` s-exprs, which stay on a single line until column 99.
+/// Tokens that would start after column 72 are wrapped onto a new line. The closing
+/// parenthesis is put on its own line if the s-expr contains nested s-exprs or
+/// wrapped tokens, and the output ends with a newline. Floats are rounded to six
+/// decimals without trailing zeros, see [`FloatFormat::kicad`]. Files written this
+/// way are left unchanged when KiCad saves them again.
+pub fn to_string_kicad<T>(value: &T) -> Result<String>
+where
+	T: ?Sized + Serialize
+{
+	let mut serializer = Serializer::kicad(String::new());
+	value.serialize(&mut serializer)?;
+	Ok(serializer.into_inner())
+}
+
+/// Serialize `value` as pretty output, using the options from `config`.
+pub fn to_string_with_config<T>(value: &T, config: &PrettyConfig) -> Result<String>
+where
+	T: ?Sized + Serialize
+{
+	let mut serializer =
+		Serializer::pretty_with_config(String::new(), config.clone());
 	value.serialize(&mut serializer)?;
-	Ok(serializer.buf)
+	Ok(serializer.into_inner())
+}
+
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+	T: ?Sized + Serialize
+{
+	let mut out = Vec::new();
+	to_writer(&mut out, value)?;
+	Ok(out)
+}
+
+pub fn to_vec_pretty<T>(value: &T) -> Result<Vec<u8>>
+where
+	T: ?Sized + Serialize
+{
+	let mut out = Vec::new();
+	to_writer_pretty(&mut out, value)?;
+	Ok(out)
+}
+
+pub fn to_vec_kicad<T>(value: &T) -> Result<Vec<u8>>
+where
+	T: ?Sized + Serialize
+{
+	let mut out = Vec::new();
+	to_writer_kicad(&mut out, value)?;
+	Ok(out)
+}
+
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+	W: io::Write,
+	T: ?Sized + Serialize
+{
+	let mut serializer = Serializer::new(IoWrite(writer));
+	value.serialize(&mut serializer)
+}
+
+pub fn to_writer_pretty<W, T>(writer: W, value: &T) -> Result<()>
+where
+	W: io::Write,
+	T: ?Sized + Serialize
+{
+	let mut serializer = Serializer::pretty(IoWrite(writer));
+	value.serialize(&mut serializer)
+}
+
+pub fn to_writer_kicad<W, T>(writer: W, value: &T) -> Result<()>
+where
+	W: io::Write,
+	T: ?Sized + Serialize
+{
+	let mut serializer = Serializer::kicad(IoWrite(writer));
+	value.serialize(&mut serializer)
+}
+
+/// Serialize `value` as pretty output to `writer`, using the options from `config`.
+pub fn to_writer_with_config<W, T>(
+	writer: W,
+	value: &T,
+	config: &PrettyConfig
+) -> Result<()>
+where
+	W: io::Write,
+	T: ?Sized + Serialize
+{
+	let mut serializer =
+		Serializer::pretty_with_config(IoWrite(writer), config.clone());
+	value.serialize(&mut serializer)
+}
+
+pub fn to_fmt_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+	W: fmt::Write,
+	T: ?Sized + Serialize
+{
+	let mut serializer = Serializer::new(writer);
+	value.serialize(&mut serializer)
+}
+
+pub fn to_fmt_writer_pretty<W, T>(writer: W, value: &T) -> Result<()>
+where
+	W: fmt::Write,
+	T: ?Sized + Serialize
+{
+	let mut serializer = Serializer::pretty(writer);
+	value.serialize(&mut serializer)
+}
+
+pub fn to_fmt_writer_kicad<W, T>(writer: W, value: &T) -> Result<()>
+where
+	W: fmt::Write,
+	T: ?Sized + Serialize
+{
+	let mut serializer = Serializer::kicad(writer);
+	value.serialize(&mut serializer)
 }
 
-impl Serializer {
-	fn newline(&mut self) {
-		self.buf += "\n";
+impl<W: Write> Serializer<W> {
+	/// Write `s` to the output, keeping track of the current column.
+	fn write_raw(&mut self, s: &str) -> Result<()> {
+		self.column = match s.rfind('\n') {
+			Some(idx) => s.len() - idx - 1,
+			None => self.column + s.len()
+		};
+		self.out.write_str(s)
+	}
+
+	fn newline(&mut self) -> Result<()> {
+		self.out.write_str("\n")?;
+		let indent = self.config.as_ref().map_or("", |config| &config.indent);
 		for _ in 0..self.lvl {
-			self.buf += "  ";
+			self.out.write_str(indent)?;
 		}
+		self.column = self.lvl * indent.len();
 		self.indent = self.lvl;
+		Ok(())
+	}
+
+	/// Remember the extra children of the current s-expr, to be written in front of
+	/// the child at their position.
+	fn begin_extra(&mut self, extra: Extra) {
+		let (items, collected) = extra.into_parts();
+		let mut items: Vec<_> = items
+			.into_iter()
+			.map(|(index, value)| (index, Pending::Value(value)))
+			.collect();
+		// children without a position stay in the order they were pushed
+		items.sort_by_key(|(index, _)| pending_order(*index));
+		items.reverse();
+		self.extra.push(PendingExtra {
+			lvl: self.lvl,
+			written: 0,
+			items,
+			collected
+		});
+	}
+
+	/// Return the original position of the child at `index` in the collection of
+	/// children named `name`, if the current s-expr recorded the positions of this
+	/// collection. The position is `None` if the child was added later.
+	fn collected_position(&self, name: &str, index: usize) -> Option<Option<usize>> {
+		let extra = self.extra.last().filter(|extra| extra.lvl == self.lvl)?;
+		let (_, positions) = extra.collected.iter().find(|(n, _)| n == name)?;
+		Some(positions.get(index).copied())
+	}
+
+	/// Write a child of the current s-expr to a buffer, and write it once the
+	/// children in front of `position` were written.
+	fn write_later<F>(&mut self, position: Option<usize>, f: F) -> Result<()>
+	where
+		F: FnOnce(&mut Self) -> Result<()>
+	{
+		let Some(mut extra) = self.extra.pop() else {
+			return f(self);
+		};
+		let cursor = self.cursor();
+		self.out.buffers.push(String::new());
+		let result = f(self);
+		let buffer = self.out.buffers.pop().unwrap_or_default();
+		let written = Pending::Written(buffer, self.cursor());
+		self.set_cursor(cursor);
+		result?;
+
+		let order = pending_order(position);
+		let at = extra
+			.items
+			.partition_point(|(index, _)| pending_order(*index) > order);
+		extra.items.insert(at, (position, written));
+		self.extra.push(extra);
+		Ok(())
+	}
+
+	fn cursor(&self) -> Cursor {
+		Cursor {
+			indent: self.indent,
+			column: self.column,
+			after_sexpr: self.after_sexpr,
+			multiline: self.multiline,
+			in_run: self.in_run
+		}
+	}
+
+	fn set_cursor(&mut self, cursor: Cursor) {
+		self.indent = cursor.indent;
+		self.column = cursor.column;
+		self.after_sexpr = cursor.after_sexpr;
+		self.multiline = cursor.multiline;
+		self.in_run = cursor.in_run;
+	}
+
+	/// Write the extra children that belong in front of the next child of the
+	/// current s-expr, or all remaining extra children if the s-expr ends.
+	fn write_extra(&mut self, end: bool) -> Result<()> {
+		let mut extra = match self.extra.pop() {
+			Some(extra) if extra.lvl == self.lvl => extra,
+			Some(extra) => {
+				self.extra.push(extra);
+				return Ok(());
+			},
+			None => return Ok(())
+		};
+		while let Some((index, _)) = extra.items.last() {
+			if !end && !matches!(index, Some(index) if *index <= extra.written) {
+				break;
+			}
+			match extra.items.pop() {
+				Some((_, Pending::Value(value))) => value.serialize(Field {
+					ser: &mut *self,
+					name: None
+				})?,
+				Some((_, Pending::Written(buffer, cursor))) => {
+					self.out.write_str(&buffer)?;
+					self.set_cursor(cursor);
+				},
+				None => {}
+			}
+			extra.written += 1;
+		}
+		if !end {
+			extra.written += 1;
+			self.extra.push(extra);
+		}
+		Ok(())
+	}
+
+	/// Write the whitespace in front of a token.
+	fn space(&mut self) -> Result<()> {
+		self.write_extra(false)?;
+		self.after_sexpr = false;
+		let Some(config) = &self.config else {
+			return self.write_raw(" ");
+		};
+
+		if self.in_run || self.column < config.max_width.unwrap_or(usize::MAX) {
+			self.write_raw(" ")
+		} else if self.inline_lvl.is_some() {
+			// KiCad does not count this space, so neither do we to produce
+			// the same line breaks
+			self.out.write_str(" ")
+		} else {
+			self.multiline = true;
+			self.newline()
+		}
 	}
 
-	fn begin_sexpr(&mut self, name: &str) {
-		if self.lvl > 0 {
-			if self.pretty {
-				self.newline();
-			} else {
-				self.buf += " ";
+	fn begin_sexpr(&mut self, name: &str) -> Result<()> {
+		self.write_extra(false)?;
+		if let Some(config) = &self.config {
+			let always_break = config.always_break.iter().any(|n| n == name);
+			let always_inline = config.always_inline.iter().any(|n| n == name);
+			let run = config.runs.iter().any(|n| n == name);
+			let inline = !always_break
+				&& (self.inline_lvl.is_some()
+					|| (self.in_run && run && self.column < config.run_width));
+			if always_inline && self.inline_lvl.is_none() {
+				self.inline_lvl = Some(self.lvl);
 			}
+
+			if self.lvl > 0 {
+				if inline {
+					self.write_raw(" ")?;
+				} else {
+					self.newline()?;
+				}
+			}
+			self.in_run = run;
+			self.after_sexpr = false;
+		} else if self.lvl > 0 {
+			self.write_raw(" ")?;
 		}
 		self.lvl += 1;
-		self.buf += "(";
-		self.buf += name;
+		self.write_raw("(")?;
+		self.write_raw(name)
 	}
 
-	fn end_sexpr(&mut self) {
+	fn end_sexpr(&mut self) -> Result<()> {
+		self.write_extra(true)?;
 		self.lvl -= 1;
-		self.buf += ")";
-	}
+		let Some(config) = &self.config else {
+			return self.write_raw(")");
+		};
 
-	fn write_integer<I: Integer>(&mut self, v: I) {
-		self.buf += " ";
-		self.buf += self.itoa_buffer.format(v);
+		let trailing_newline = self.lvl == 0 && config.trailing_newline;
+		if config.closing_paren_newline
+			&& self.inline_lvl.is_none()
+			&& (self.after_sexpr || self.multiline)
+		{
+			self.newline()?;
+			self.multiline = false;
+		}
+		self.write_raw(")")?;
+		self.after_sexpr = true;
+		if self.inline_lvl == Some(self.lvl) {
+			self.inline_lvl = None;
+		}
+		if trailing_newline {
+			self.write_raw("\n")?;
+		}
+		Ok(())
 	}
 
-	fn write_float<F: ToString>(&mut self, v: F) {
-		self.buf += " ";
-		self.buf += &v.to_string();
+	fn write_integer<I: Integer>(&mut self, v: I) -> Result<()> {
+		self.space()?;
+		let v = self.itoa_buffer.format(v);
+		self.column += v.len();
+		self.out.write_str(v)
 	}
 
-	fn write_str(&mut self, v: &str, aggressive_quotes: bool) {
-		self.buf += " ";
+	fn write_float<F: Float>(&mut self, v: F) -> Result<()> {
+		if self.strict_floats && !v.is_finite() {
+			return Err(Error::NonFiniteFloat);
+		}
+		self.space()?;
+		let v = self.float_format.format(v);
+		self.write_raw(&v)
+	}
 
-		const CHARS: &[char] = &[' ', '\t', '\n', '\r', '(', ')', '"'];
-		let need_quotes = v.is_empty()
-			|| match aggressive_quotes {
-				true => v.chars().any(|ch| !ch.is_ascii_alphabetic() && ch != '_'),
-				false => v.contains(CHARS)
-			};
+	/// Write a float with all of its digits, regardless of the float format.
+	fn write_exact_float(&mut self, v: f64) -> Result<()> {
+		if self.strict_floats && !v.is_finite() {
+			return Err(Error::NonFiniteFloat);
+		}
+		self.space()?;
+		self.write_raw(&format_exact(v))
+	}
 
-		if need_quotes {
-			self.buf += r#"""#;
-			self.buf += &v.replace('\\', r"\\").replace('"', r#"\""#);
-			self.buf += r#"""#;
+	fn write_str(&mut self, v: &str, aggressive_quotes: bool) -> Result<()> {
+		self.space()?;
+		if needs_quotes(v, aggressive_quotes) {
+			self.write_quoted(v)
 		} else {
-			self.buf += v;
+			self.write_raw(v)
+		}
+	}
+
+	fn write_quoted(&mut self, v: &str) -> Result<()> {
+		self.write_raw(r#"""#)?;
+		self.write_raw(&escape(v))?;
+		self.write_raw(r#"""#)
+	}
+}
+
+/// Check whether the string `v` needs to be quoted. If `aggressive_quotes` is set,
+/// everything but simple identifiers is quoted.
+pub(crate) fn needs_quotes(v: &str, aggressive_quotes: bool) -> bool {
+	const CHARS: &[char] = &[' ', '\t', '\n', '\r', '(', ')', '"'];
+	v.is_empty()
+		|| match aggressive_quotes {
+			true => v.chars().any(|ch| !ch.is_ascii_alphabetic() && ch != '_'),
+			false => v.contains(CHARS)
+		}
+}
+
+/// Escape the string `v` so that it can be placed inside quotes. Like KiCad, this
+/// escapes backslashes, quotes and line breaks, and keeps everything else as is.
+pub(crate) fn escape(v: &str) -> String {
+	let mut escaped = String::with_capacity(v.len());
+	for ch in v.chars() {
+		match ch {
+			'\\' => escaped.push_str(r"\\"),
+			'"' => escaped.push_str(r#"\""#),
+			'\n' => escaped.push_str(r"\n"),
+			'\r' => escaped.push_str(r"\r"),
+			ch => escaped.push(ch)
 		}
 	}
+	escaped
 }
 
 macro_rules! serialize_type_error {
@@ -158,17 +702,17 @@ macro_rules! serialize_type_error {
 
 type Impossible<T = (), E = Error> = serde::ser::Impossible<T, E>;
 
-impl<'a> ser::Serializer for &'a mut Serializer {
+impl<W: Write> ser::Serializer for &mut Serializer<W> {
 	type Ok = ();
 	type Error = Error;
 
 	type SerializeSeq = Impossible;
 	type SerializeTuple = Impossible;
 	type SerializeTupleStruct = Self;
-	type SerializeTupleVariant = Impossible;
+	type SerializeTupleVariant = Self;
 	type SerializeMap = Impossible;
 	type SerializeStruct = Self;
-	type SerializeStructVariant = Impossible;
+	type SerializeStructVariant = Self;
 
 	serialize_type_error! {
 		fn serialize_bool(self, bool);
@@ -191,34 +735,32 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 		fn serialize_some<T>(self, &T);
 		fn serialize_unit(self);
 		fn serialize_unit_variant(self, &'static str, u32, &'static str);
-		fn serialize_newtype_variant<T>(self, &'static str, u32, &'static str, &T);
 	}
 
 	serialize_type_error! {
 		fn serialize_seq(self, Option<usize>) -> Result<Impossible>;
 		fn serialize_tuple(self, usize) -> Result<Impossible>;
-		fn serialize_tuple_variant(self, &'static str, u32, &'static str, usize) -> Result<Impossible>;
 		fn serialize_map(self, Option<usize>) -> Result<Impossible>;
-		fn serialize_struct_variant(self, &'static str, u32, &'static str, usize) -> Result<Impossible>;
 	}
 
 	fn serialize_unit_struct(self, name: &'static str) -> Result<()> {
-		self.begin_sexpr(name);
-		self.end_sexpr();
-		Ok(())
+		self.begin_sexpr(name)?;
+		self.end_sexpr()
 	}
 
 	fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
 	where
 		T: ?Sized + Serialize
 	{
-		self.begin_sexpr(name);
+		if name == SYMBOL || name == STRING || name == EXACT_FLOAT || name == EXTRA {
+			return Err(Error::ExpectedStruct);
+		}
+		self.begin_sexpr(name)?;
 		value.serialize(Field {
 			ser: &mut *self,
 			name: None
 		})?;
-		self.end_sexpr();
-		Ok(())
+		self.end_sexpr()
 	}
 
 	fn serialize_tuple_struct(
@@ -226,17 +768,58 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 		name: &'static str,
 		_len: usize
 	) -> Result<Self> {
-		self.begin_sexpr(name);
+		// untyped s-exprs will begin once we know their name
+		if name == LIST {
+			self.head_pending = true;
+		} else {
+			self.begin_sexpr(name)?;
+		}
 		Ok(self)
 	}
 
 	fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Self> {
-		self.begin_sexpr(name);
+		self.begin_sexpr(name)?;
+		Ok(self)
+	}
+
+	fn serialize_newtype_variant<T>(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		value: &T
+	) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		// the content of a newtype variant is named like the variant
+		value.serialize(self)
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		variant: &'static str,
+		_len: usize
+	) -> Result<Self> {
+		self.begin_sexpr(variant)?;
+		Ok(self)
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		variant: &'static str,
+		_len: usize
+	) -> Result<Self> {
+		self.begin_sexpr(variant)?;
 		Ok(self)
 	}
 }
 
-impl<'a> SerializeTupleStruct for &'a mut Serializer {
+impl<W: Write> SerializeTupleStruct for &mut Serializer<W> {
 	type Ok = ();
 	type Error = Error;
 
@@ -244,6 +827,13 @@ impl<'a> SerializeTupleStruct for &'a mut Serializer {
 	where
 		T: ?Sized + Serialize
 	{
+		if self.head_pending {
+			self.head_pending = false;
+			return value.serialize(Token {
+				ser: &mut **self,
+				kind: TokenKind::Head
+			});
+		}
 		value.serialize(Field {
 			ser: &mut **self,
 			name: None
@@ -251,12 +841,11 @@ impl<'a> SerializeTupleStruct for &'a mut Serializer {
 	}
 
 	fn end(self) -> Result<()> {
-		self.end_sexpr();
-		Ok(())
+		self.end_sexpr()
 	}
 }
 
-impl<'a> SerializeStruct for &'a mut Serializer {
+impl<W: Write> SerializeStruct for &mut Serializer<W> {
 	type Ok = ();
 	type Error = Error;
 
@@ -272,15 +861,49 @@ impl<'a> SerializeStruct for &'a mut Serializer {
 	}
 
 	fn end(self) -> Result<()> {
-		self.end_sexpr();
-		Ok(())
+		self.end_sexpr()
+	}
+}
+
+impl<W: Write> SerializeTupleVariant for &mut Serializer<W> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		value.serialize(Field {
+			ser: &mut **self,
+			name: None
+		})
+	}
+
+	fn end(self) -> Result<()> {
+		self.end_sexpr()
+	}
+}
+
+impl<W: Write> SerializeStructVariant for &mut Serializer<W> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		SerializeStruct::serialize_field(self, key, value)
+	}
+
+	fn end(self) -> Result<()> {
+		self.end_sexpr()
 	}
 }
 
 /// This serializer will serialize all fields. It needs the field name for booleans and
 /// sequences.
-struct Field<'a> {
-	ser: &'a mut Serializer,
+struct Field<'a, W> {
+	ser: &'a mut Serializer<W>,
 	name: Option<&'static str>
 }
 
@@ -289,36 +912,28 @@ macro_rules! serialize_integer {
 		$(
 			paste! {
 				fn [<serialize_ $integer>](self, v: $integer) -> Result<()> {
-					self.ser.write_integer(v);
-					Ok(())
+					self.ser.write_integer(v)
 				}
 			}
 		)+
 	};
 }
 
-impl<'a> ser::Serializer for Field<'a> {
+impl<'a, W: Write + 'a> ser::Serializer for Field<'a, W> {
 	type Ok = ();
 	type Error = Error;
 
-	type SerializeSeq = Sequence<'a>;
-	type SerializeTuple = Sequence<'a>;
-	type SerializeTupleStruct = &'a mut Serializer;
-	type SerializeTupleVariant = Impossible;
-	type SerializeMap = Impossible;
-	type SerializeStruct = &'a mut Serializer;
-	type SerializeStructVariant = Impossible;
+	type SerializeSeq = Sequence<'a, W>;
+	type SerializeTuple = Sequence<'a, W>;
+	type SerializeTupleStruct = &'a mut Serializer<W>;
+	type SerializeTupleVariant = &'a mut Serializer<W>;
+	type SerializeMap = Entries<'a, W>;
+	type SerializeStruct = &'a mut Serializer<W>;
+	type SerializeStructVariant = &'a mut Serializer<W>;
 
 	serialize_type_error! {
 		fn serialize_char(self, char) = Error::Char;
 		fn serialize_bytes(self, &[u8]) = Error::Bytes;
-		fn serialize_newtype_variant<T>(self, &'static str, u32, &'static str, &T) = Error::ComplexEnum;
-	}
-
-	serialize_type_error! {
-		fn serialize_tuple_variant(self, &'static str, u32, &'static str, usize) -> Result<Impossible> = Error::ComplexEnum;
-		fn serialize_map(self, Option<usize>) -> Result<Impossible> = Error::Map;
-		fn serialize_struct_variant(self, &'static str, u32, &'static str, usize) -> Result<Impossible> = Error::ComplexEnum;
 	}
 
 	fn serialize_bool(self, v: bool) -> Result<()> {
@@ -334,18 +949,15 @@ impl<'a> ser::Serializer for Field<'a> {
 	}
 
 	fn serialize_f32(self, v: f32) -> Result<()> {
-		self.ser.write_float(v);
-		Ok(())
+		self.ser.write_float(v)
 	}
 
 	fn serialize_f64(self, v: f64) -> Result<()> {
-		self.ser.write_float(v);
-		Ok(())
+		self.ser.write_float(v)
 	}
 
 	fn serialize_str(self, v: &str) -> Result<()> {
-		self.ser.write_str(v, true);
-		Ok(())
+		self.ser.write_str(v, true)
 	}
 
 	fn serialize_none(self) -> Result<()> {
@@ -374,30 +986,53 @@ impl<'a> ser::Serializer for Field<'a> {
 		_variant_index: u32,
 		variant: &'static str
 	) -> Result<()> {
-		self.ser.write_str(variant, false);
-		Ok(())
+		self.ser.write_str(variant, false)
 	}
 
 	fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
 	where
 		T: ?Sized + Serialize
 	{
-		self.ser.serialize_newtype_struct(name, value)
+		let kind = match name {
+			SYMBOL => TokenKind::Symbol,
+			STRING => TokenKind::String,
+			EXACT_FLOAT => {
+				return match value.serialize(ValueSerializer)? {
+					Value::Float(v) => self.ser.write_exact_float(v),
+					_ => Err(ser::Error::custom("Expected float"))
+				};
+			},
+			EXTRA => {
+				let extra = Extra::from_value(value.serialize(ValueSerializer)?)
+					.ok_or(Error::InvalidExtra)?;
+				self.ser.begin_extra(extra);
+				return Ok(());
+			},
+			_ => return self.ser.serialize_newtype_struct(name, value)
+		};
+		value.serialize(Token {
+			ser: self.ser,
+			kind
+		})
 	}
 
-	fn serialize_seq(self, _len: Option<usize>) -> Result<Sequence<'a>> {
+	fn serialize_seq(self, _len: Option<usize>) -> Result<Sequence<'a, W>> {
 		let name = self.name.ok_or(Error::UnnamedSeq)?;
 		let close_sexpr = match name {
+			// the elements of collections are children of the current s-expr
 			"" => false,
+			name if collection_name(name).is_some() => false,
 			name => {
-				self.ser.begin_sexpr(name);
+				self.ser.begin_sexpr(name)?;
 				true
 			}
 		};
-		Ok(Sequence::new(self.ser, close_sexpr))
+		let mut seq = Sequence::new(self.ser, close_sexpr);
+		seq.collection = collection_name(name);
+		Ok(seq)
 	}
 
-	fn serialize_tuple(self, len: usize) -> Result<Sequence<'a>> {
+	fn serialize_tuple(self, len: usize) -> Result<Sequence<'a, W>> {
 		self.serialize_seq(Some(len))
 	}
 
@@ -405,54 +1040,366 @@ impl<'a> ser::Serializer for Field<'a> {
 		self,
 		name: &'static str,
 		len: usize
-	) -> Result<&'a mut Serializer> {
+	) -> Result<&'a mut Serializer<W>> {
 		self.ser.serialize_tuple_struct(name, len)
 	}
 
+	fn serialize_map(self, _len: Option<usize>) -> Result<Entries<'a, W>> {
+		match self.name {
+			Some(name) if !name.is_empty() => Ok(Entries {
+				ser: self.ser,
+				name
+			}),
+			_ => Err(Error::Map)
+		}
+	}
+
 	fn serialize_struct(
 		self,
 		name: &'static str,
 		len: usize
-	) -> Result<&'a mut Serializer> {
+	) -> Result<&'a mut Serializer<W>> {
 		self.ser.serialize_struct(name, len)
 	}
-}
 
-/// A sequence / tuple serializer that optionally closes an s-expr afterwards
-struct Sequence<'a> {
-	ser: &'a mut Serializer,
-	close_sexpr: bool
-}
+	fn serialize_newtype_variant<T>(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		value: &T
+	) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		value.serialize(self)
+	}
 
-impl<'a> Sequence<'a> {
-	fn new(ser: &'a mut Serializer, close_sexpr: bool) -> Self {
-		Self { ser, close_sexpr }
+	fn serialize_tuple_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		len: usize
+	) -> Result<&'a mut Serializer<W>> {
+		self.ser
+			.serialize_tuple_variant(name, variant_index, variant, len)
+	}
+
+	fn serialize_struct_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		len: usize
+	) -> Result<&'a mut Serializer<W>> {
+		self.ser
+			.serialize_struct_variant(name, variant_index, variant, len)
 	}
 }
 
-impl<'a> SerializeSeq for Sequence<'a> {
+/// This serializer writes every entry of a map as an s-expr named like the map's
+/// field, containing the key followed by the value.
+struct Entries<'a, W> {
+	ser: &'a mut Serializer<W>,
+	name: &'static str
+}
+
+impl<'a, W: Write> SerializeMap for Entries<'a, W> {
 	type Ok = ();
 	type Error = Error;
 
-	fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+	fn serialize_key<T>(&mut self, key: &T) -> Result<()>
 	where
 		T: ?Sized + Serialize
 	{
-		value.serialize(Field {
+		self.ser.begin_sexpr(self.name)?;
+		key.serialize(Field {
 			ser: &mut *self.ser,
 			name: None
 		})
 	}
 
+	fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		value.serialize(EntryValue {
+			ser: &mut *self.ser
+		})?;
+		self.ser.end_sexpr()
+	}
+
+	fn end(self) -> Result<()> {
+		Ok(())
+	}
+}
+
+/// This serializer writes the value of a map entry. Structs and tuples are written
+/// without an s-expr of their own, so that their content follows the key.
+struct EntryValue<'a, W> {
+	ser: &'a mut Serializer<W>
+}
+
+macro_rules! forward_to_field {
+	($(fn $ident:ident $(<$T:ident>)? (self $(, $arg:ident: $arg_ty:ty)*);)+) => {
+		$(
+			fn $ident $(<$T>)? (self $(, $arg: $arg_ty)*) -> Result<()>
+			$(where $T: ?Sized + Serialize)?
+			{
+				Field {
+					ser: self.ser,
+					name: None
+				}
+				.$ident($($arg),*)
+			}
+		)+
+	};
+}
+
+impl<'a, W: Write + 'a> ser::Serializer for EntryValue<'a, W> {
+	type Ok = ();
+	type Error = Error;
+
+	type SerializeSeq = Sequence<'a, W>;
+	type SerializeTuple = Sequence<'a, W>;
+	type SerializeTupleStruct = Sequence<'a, W>;
+	type SerializeTupleVariant = &'a mut Serializer<W>;
+	type SerializeMap = Impossible;
+	type SerializeStruct = Sequence<'a, W>;
+	type SerializeStructVariant = &'a mut Serializer<W>;
+
+	forward_to_field! {
+		fn serialize_bool(self, v: bool);
+		fn serialize_i8(self, v: i8);
+		fn serialize_i16(self, v: i16);
+		fn serialize_i32(self, v: i32);
+		fn serialize_i64(self, v: i64);
+		fn serialize_i128(self, v: i128);
+		fn serialize_u8(self, v: u8);
+		fn serialize_u16(self, v: u16);
+		fn serialize_u32(self, v: u32);
+		fn serialize_u64(self, v: u64);
+		fn serialize_u128(self, v: u128);
+		fn serialize_f32(self, v: f32);
+		fn serialize_f64(self, v: f64);
+		fn serialize_char(self, v: char);
+		fn serialize_str(self, v: &str);
+		fn serialize_bytes(self, v: &[u8]);
+		fn serialize_none(self);
+		fn serialize_some<T>(self, v: &T);
+		fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str);
+		fn serialize_newtype_variant<T>(self, name: &'static str, index: u32, variant: &'static str, v: &T);
+	}
+
+	serialize_type_error! {
+		fn serialize_map(self, Option<usize>) -> Result<Impossible> = Error::Map;
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		len: usize
+	) -> Result<&'a mut Serializer<W>> {
+		self.ser
+			.serialize_tuple_variant(name, variant_index, variant, len)
+	}
+
+	fn serialize_struct_variant(
+		self,
+		name: &'static str,
+		variant_index: u32,
+		variant: &'static str,
+		len: usize
+	) -> Result<&'a mut Serializer<W>> {
+		self.ser
+			.serialize_struct_variant(name, variant_index, variant, len)
+	}
+
+	fn serialize_unit(self) -> Result<()> {
+		Ok(())
+	}
+
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+		Ok(())
+	}
+
+	fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		let field = Field {
+			ser: self.ser,
+			name: None
+		};
+		match name {
+			SYMBOL | STRING | EXACT_FLOAT => {
+				field.serialize_newtype_struct(name, value)
+			},
+			_ => value.serialize(field)
+		}
+	}
+
+	fn serialize_seq(self, _len: Option<usize>) -> Result<Sequence<'a, W>> {
+		Ok(Sequence::new(self.ser, false))
+	}
+
+	fn serialize_tuple(self, _len: usize) -> Result<Sequence<'a, W>> {
+		Ok(Sequence::new(self.ser, false))
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		_name: &'static str,
+		_len: usize
+	) -> Result<Sequence<'a, W>> {
+		Ok(Sequence::new(self.ser, false))
+	}
+
+	fn serialize_struct(
+		self,
+		_name: &'static str,
+		_len: usize
+	) -> Result<Sequence<'a, W>> {
+		Ok(Sequence::new(self.ser, false))
+	}
+}
+
+enum TokenKind {
+	/// The name of an untyped s-expr.
+	Head,
+
+	/// A symbol, i.e. a string that is only quoted if necessary.
+	Symbol,
+
+	/// A string that is always quoted.
+	String
+}
+
+/// This serializer will serialize a single string token of an untyped s-expr.
+struct Token<'a, W> {
+	ser: &'a mut Serializer<W>,
+	kind: TokenKind
+}
+
+impl<'a, W: Write> ser::Serializer for Token<'a, W> {
+	type Ok = ();
+	type Error = Error;
+
+	type SerializeSeq = Impossible;
+	type SerializeTuple = Impossible;
+	type SerializeTupleStruct = Impossible;
+	type SerializeTupleVariant = Impossible;
+	type SerializeMap = Impossible;
+	type SerializeStruct = Impossible;
+	type SerializeStructVariant = Impossible;
+
+	serialize_type_error! {
+		fn serialize_bool(self, bool) = Error::ExpectedString;
+		fn serialize_i8(self, i8) = Error::ExpectedString;
+		fn serialize_i16(self, i16) = Error::ExpectedString;
+		fn serialize_i32(self, i32) = Error::ExpectedString;
+		fn serialize_i64(self, i64) = Error::ExpectedString;
+		fn serialize_i128(self, i128) = Error::ExpectedString;
+		fn serialize_u8(self, u8) = Error::ExpectedString;
+		fn serialize_u16(self, u16) = Error::ExpectedString;
+		fn serialize_u32(self, u32) = Error::ExpectedString;
+		fn serialize_u64(self, u64) = Error::ExpectedString;
+		fn serialize_u128(self, u128) = Error::ExpectedString;
+		fn serialize_f32(self, f32) = Error::ExpectedString;
+		fn serialize_f64(self, f64) = Error::ExpectedString;
+		fn serialize_char(self, char) = Error::ExpectedString;
+		fn serialize_bytes(self, &[u8]) = Error::ExpectedString;
+		fn serialize_none(self) = Error::ExpectedString;
+		fn serialize_some<T>(self, &T) = Error::ExpectedString;
+		fn serialize_unit(self) = Error::ExpectedString;
+		fn serialize_unit_struct(self, &'static str) = Error::ExpectedString;
+		fn serialize_unit_variant(self, &'static str, u32, &'static str) = Error::ExpectedString;
+		fn serialize_newtype_struct<T>(self, &'static str, &T) = Error::ExpectedString;
+		fn serialize_newtype_variant<T>(self, &'static str, u32, &'static str, &T) = Error::ExpectedString;
+	}
+
+	serialize_type_error! {
+		fn serialize_seq(self, Option<usize>) -> Result<Impossible> = Error::ExpectedString;
+		fn serialize_tuple(self, usize) -> Result<Impossible> = Error::ExpectedString;
+		fn serialize_tuple_struct(self, &'static str, usize) -> Result<Impossible> = Error::ExpectedString;
+		fn serialize_tuple_variant(self, &'static str, u32, &'static str, usize) -> Result<Impossible> = Error::ExpectedString;
+		fn serialize_map(self, Option<usize>) -> Result<Impossible> = Error::ExpectedString;
+		fn serialize_struct(self, &'static str, usize) -> Result<Impossible> = Error::ExpectedString;
+		fn serialize_struct_variant(self, &'static str, u32, &'static str, usize) -> Result<Impossible> = Error::ExpectedString;
+	}
+
+	fn serialize_str(self, v: &str) -> Result<()> {
+		match self.kind {
+			TokenKind::Head => self.ser.begin_sexpr(v),
+			TokenKind::Symbol => self.ser.write_str(v, false),
+			TokenKind::String => {
+				self.ser.space()?;
+				self.ser.write_quoted(v)
+			}
+		}
+	}
+}
+
+/// A sequence / tuple / struct serializer that optionally closes an s-expr afterwards
+struct Sequence<'a, W> {
+	ser: &'a mut Serializer<W>,
+	close_sexpr: bool,
+
+	/// The name of the children if this is a collection field
+	collection: Option<&'static str>,
+
+	/// The index of the next element
+	index: usize
+}
+
+impl<'a, W: Write> Sequence<'a, W> {
+	fn new(ser: &'a mut Serializer<W>, close_sexpr: bool) -> Self {
+		Self {
+			ser,
+			close_sexpr,
+			collection: None,
+			index: 0
+		}
+	}
+}
+
+impl<'a, W: Write> SerializeSeq for Sequence<'a, W> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		let index = self.index;
+		self.index += 1;
+		let position = self
+			.collection
+			.and_then(|name| self.ser.collected_position(name, index));
+		let Some(position) = position else {
+			return value.serialize(Field {
+				ser: &mut *self.ser,
+				name: None
+			});
+		};
+		// the children of a collection are moved back to their original position
+		self.ser.write_later(position, |ser| {
+			value.serialize(Field { ser, name: None })
+		})
+	}
+
 	fn end(self) -> Result<()> {
 		if self.close_sexpr {
-			self.ser.end_sexpr();
+			self.ser.end_sexpr()?;
 		}
 		Ok(())
 	}
 }
 
-impl<'a> SerializeTuple for Sequence<'a> {
+impl<'a, W: Write> SerializeTuple for Sequence<'a, W> {
 	type Ok = ();
 	type Error = Error;
 
@@ -467,3 +1414,218 @@ impl<'a> SerializeTuple for Sequence<'a> {
 		SerializeSeq::end(self)
 	}
 }
+
+impl<'a, W: Write> SerializeTupleStruct for Sequence<'a, W> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		SerializeSeq::serialize_element(self, value)
+	}
+
+	fn end(self) -> Result<()> {
+		SerializeSeq::end(self)
+	}
+}
+
+impl<'a, W: Write> SerializeStruct for Sequence<'a, W> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		value.serialize(Field {
+			ser: &mut *self.ser,
+			name: Some(key)
+		})
+	}
+
+	fn end(self) -> Result<()> {
+		SerializeSeq::end(self)
+	}
+}
+
+/// This serializer turns the output of [`Value`]'s `Serialize` implementation back
+/// into a [`Value`], so that it can be kept until it gets written. Sequences and
+/// tuples become s-exprs with an empty name.
+pub(crate) struct ValueSerializer;
+
+macro_rules! serialize_value {
+	($($ident:ident($ty:ty) => $variant:ident;)+) => {
+		$(
+			fn $ident(self, v: $ty) -> Result<Value> {
+				Ok(Value::$variant(v.into()))
+			}
+		)+
+	};
+}
+
+impl ser::Serializer for ValueSerializer {
+	type Ok = Value;
+	type Error = Error;
+
+	type SerializeSeq = ValueList;
+	type SerializeTuple = ValueList;
+	type SerializeTupleStruct = ValueList;
+	type SerializeTupleVariant = Impossible<Value>;
+	type SerializeMap = Impossible<Value>;
+	type SerializeStruct = Impossible<Value>;
+	type SerializeStructVariant = Impossible<Value>;
+
+	serialize_type_error! {
+		fn serialize_bool(self, bool) = Error::InvalidExtra;
+		fn serialize_i128(self, i128) = Error::InvalidExtra;
+		fn serialize_u128(self, u128) = Error::InvalidExtra;
+		fn serialize_char(self, char) = Error::InvalidExtra;
+		fn serialize_bytes(self, &[u8]) = Error::InvalidExtra;
+		fn serialize_none(self) = Error::InvalidExtra;
+		fn serialize_some<T>(self, &T) = Error::InvalidExtra;
+		fn serialize_unit(self) = Error::InvalidExtra;
+		fn serialize_unit_struct(self, &'static str) = Error::InvalidExtra;
+		fn serialize_unit_variant(self, &'static str, u32, &'static str) = Error::InvalidExtra;
+		fn serialize_newtype_variant<T>(self, &'static str, u32, &'static str, &T) = Error::InvalidExtra;
+	}
+
+	serialize_type_error! {
+		fn serialize_tuple_variant(self, &'static str, u32, &'static str, usize) -> Result<Impossible<Value>> = Error::InvalidExtra;
+		fn serialize_map(self, Option<usize>) -> Result<Impossible<Value>> = Error::InvalidExtra;
+		fn serialize_struct(self, &'static str, usize) -> Result<Impossible<Value>> = Error::InvalidExtra;
+		fn serialize_struct_variant(self, &'static str, u32, &'static str, usize) -> Result<Impossible<Value>> = Error::InvalidExtra;
+	}
+
+	serialize_value! {
+		serialize_i8(i8) => Int;
+		serialize_i16(i16) => Int;
+		serialize_i32(i32) => Int;
+		serialize_i64(i64) => Int;
+		serialize_u8(u8) => Int;
+		serialize_u16(u16) => Int;
+		serialize_u32(u32) => Int;
+		serialize_f32(f32) => Float;
+		serialize_f64(f64) => Float;
+		serialize_str(&str) => String;
+	}
+
+	fn serialize_u64(self, v: u64) -> Result<Value> {
+		Ok(match i64::try_from(v) {
+			Ok(v) => Value::Int(v),
+			Err(_) => Value::Symbol(v.to_string())
+		})
+	}
+
+	fn serialize_newtype_struct<T>(
+		self,
+		name: &'static str,
+		value: &T
+	) -> Result<Value>
+	where
+		T: ?Sized + Serialize
+	{
+		match (name, value.serialize(self)?) {
+			(SYMBOL, Value::String(symbol)) => Ok(Value::Symbol(symbol)),
+			(_, value) => Ok(value)
+		}
+	}
+
+	fn serialize_seq(self, _len: Option<usize>) -> Result<ValueList> {
+		Ok(ValueList {
+			head: Some(String::new()),
+			items: Vec::new()
+		})
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<ValueList> {
+		self.serialize_seq(Some(len))
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		name: &'static str,
+		len: usize
+	) -> Result<ValueList> {
+		let mut list = self.serialize_seq(Some(len))?;
+		// the name of an untyped s-expr is its first field
+		if name == LIST {
+			list.head = None;
+		}
+		Ok(list)
+	}
+}
+
+/// The s-expr that gets built by a [`ValueSerializer`].
+pub(crate) struct ValueList {
+	head: Option<String>,
+	items: Vec<Value>
+}
+
+impl ValueList {
+	fn push<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		match (&self.head, value.serialize(ValueSerializer)?) {
+			(None, Value::String(head)) => self.head = Some(head),
+			(None, _) => return Err(Error::ExpectedString),
+			(Some(_), value) => self.items.push(value)
+		}
+		Ok(())
+	}
+
+	fn end(self) -> Result<Value> {
+		let head = self.head.ok_or(Error::ExpectedString)?;
+		Ok(Value::List(head, self.items))
+	}
+}
+
+impl SerializeSeq for ValueList {
+	type Ok = Value;
+	type Error = Error;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		self.push(value)
+	}
+
+	fn end(self) -> Result<Value> {
+		ValueList::end(self)
+	}
+}
+
+impl SerializeTuple for ValueList {
+	type Ok = Value;
+	type Error = Error;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		self.push(value)
+	}
+
+	fn end(self) -> Result<Value> {
+		ValueList::end(self)
+	}
+}
+
+impl SerializeTupleStruct for ValueList {
+	type Ok = Value;
+	type Error = Error;
+
+	fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize
+	{
+		self.push(value)
+	}
+
+	fn end(self) -> Result<Value> {
+		ValueList::end(self)
+	}
+}
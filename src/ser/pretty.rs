@@ -0,0 +1,149 @@
+/// Options for pretty output.
+///
+/// Pretty output puts every nested s-expr on its own line. The options below change
+/// the indentation, allow certain s-exprs to be written on a single line,
+/// and wrap long lines. All decisions are made while writing, without looking ahead,
+/// so a line can exceed the configured width by the length of its last token.
+///
+/// ### Example
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_kicad_sexpr::ser::PrettyConfig;
+///
+/// #[derive(Serialize)]
+/// #[serde(rename = "at")]
+/// struct Position(f32, f32);
+///
+/// #[derive(Serialize)]
+/// #[serde(rename = "text")]
+/// struct Text {
+///     content: String,
+///     at: Position
+/// }
+///
+/// let text = Text {
+///     content: "Hello World".to_owned(),
+///     at: Position(1.0, 2.0)
+/// };
+///
+/// let config = PrettyConfig::new().indent("\t").closing_paren_newline(true);
+/// let written = serde_kicad_sexpr::to_string_with_config(&text, &config).unwrap();
+/// assert_eq!(written, "(text \"Hello World\"\n\t(at 1 2)\n)");
+///
+/// let config = PrettyConfig::new().always_inline(["text"]);
+/// let written = serde_kicad_sexpr::to_string_with_config(&text, &config).unwrap();
+/// assert_eq!(written, "(text \"Hello World\" (at 1 2))");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrettyConfig {
+	pub(super) indent: String,
+	pub(super) max_width: Option<usize>,
+	pub(super) always_inline: Vec<String>,
+	pub(super) always_break: Vec<String>,
+	pub(super) runs: Vec<String>,
+	pub(super) run_width: usize,
+	pub(super) closing_paren_newline: bool,
+	pub(super) trailing_newline: bool
+}
+
+impl PrettyConfig {
+	/// The options used by [`to_string_pretty`](super::to_string_pretty): Indent by
+	/// two spaces, never wrap tokens, and keep closing parentheses on the same line.
+	pub fn new() -> Self {
+		Self {
+			indent: "  ".to_owned(),
+			max_width: None,
+			always_inline: Vec::new(),
+			always_break: Vec::new(),
+			runs: Vec::new(),
+			run_width: 0,
+			closing_paren_newline: false,
+			trailing_newline: false
+		}
+	}
+
+	/// The options used by [`to_string_kicad`](super::to_string_kicad), which
+	/// produce the same output as KiCad's own formatter.
+	pub fn kicad() -> Self {
+		Self {
+			indent: "\t".to_owned(),
+			max_width: Some(72),
+			always_inline: Vec::new(),
+			always_break: Vec::new(),
+			runs: vec!["xy".to_owned()],
+			run_width: 99,
+			closing_paren_newline: true,
+			trailing_newline: true
+		}
+	}
+
+	/// Set the string used for one level of indentation, e.g. `"\t"` or `"    "`.
+	pub fn indent(mut self, indent: impl Into<String>) -> Self {
+		self.indent = indent.into();
+		self
+	}
+
+	/// Wrap tokens onto a new line once the current line is at least `max_width`
+	/// bytes wide. If the tokens of an s-expr get wrapped, its closing parenthesis
+	/// is treated like that of an s-expr with nested s-exprs.
+	pub fn max_width(mut self, max_width: Option<usize>) -> Self {
+		self.max_width = max_width;
+		self
+	}
+
+	/// Always write s-exprs with these names on a single line, including everything
+	/// inside them. The s-expr itself still starts on a new line. KiCad can do this
+	/// for s-exprs like `(font ..)` or `(stroke ..)`.
+	pub fn always_inline<I, S>(mut self, names: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>
+	{
+		self.always_inline.extend(names.into_iter().map(Into::into));
+		self
+	}
+
+	/// Always start s-exprs with these names on a new line, even inside an
+	/// [`always_inline`](Self::always_inline) s-expr or a [run](Self::runs).
+	pub fn always_break<I, S>(mut self, names: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>
+	{
+		self.always_break.extend(names.into_iter().map(Into::into));
+		self
+	}
+
+	/// Keep consecutive s-exprs with one of these names on the same line, until
+	/// the line is at least `width` bytes wide. Tokens inside these s-exprs are
+	/// never wrapped. KiCad does this for `(xy ..)` points.
+	pub fn runs<I, S>(mut self, names: I, width: usize) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>
+	{
+		self.runs.extend(names.into_iter().map(Into::into));
+		self.run_width = width;
+		self
+	}
+
+	/// Put the closing parenthesis of an s-expr on its own line if the s-expr
+	/// spans multiple lines.
+	pub fn closing_paren_newline(mut self, closing_paren_newline: bool) -> Self {
+		self.closing_paren_newline = closing_paren_newline;
+		self
+	}
+
+	/// End the output with a newline.
+	pub fn trailing_newline(mut self, trailing_newline: bool) -> Self {
+		self.trailing_newline = trailing_newline;
+		self
+	}
+}
+
+impl Default for PrettyConfig {
+	fn default() -> Self {
+		Self::new()
+	}
+}
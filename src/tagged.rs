@@ -0,0 +1,250 @@
+/// Define an enum whose variant is selected by a value inside of the s-expr, rather
+/// than by the name of the s-expr.
+///
+/// The `#[tag(..)]` attribute has to come first and describes where to find the
+/// tag. Every segment of its path either enters the child s-expr with that name, or
+/// skips that many values of the current s-expr. The tag is the value after the
+/// last segment. Every variant wraps a type that is deserialized from the whole
+/// s-expr, and is selected by the value of the tag after the `=`.
+///
+/// ### Example
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, Serialize)]
+/// #[serde(rename = "pad")]
+/// struct SmdPad {
+///     index: String,
+///     ty: String,
+///     layers: Vec<String>
+/// }
+///
+/// #[derive(Deserialize, Serialize)]
+/// #[serde(rename = "drill")]
+/// struct Drill(f32);
+///
+/// #[derive(Deserialize, Serialize)]
+/// #[serde(rename = "pad")]
+/// struct ThtPad {
+///     index: String,
+///     ty: String,
+///     drill: Drill
+/// }
+///
+/// serde_kicad_sexpr::tagged! {
+///     // the tag is the second value of the s-expr, i.e. after the index
+///     #[tag(1)]
+///     enum Pad {
+///         Smd(SmdPad) = "smd",
+///         ThroughHole(ThtPad) = "thru_hole"
+///     }
+/// }
+///
+/// let input = r#"(pad "1" thru_hole (drill 0.8))"#;
+/// let pad: Pad = serde_kicad_sexpr::from_str(input).unwrap();
+/// assert!(matches!(pad, Pad::ThroughHole(_)));
+/// ```
+///
+/// A path like `#[tag(effects, justify)]` finds the tag `left` in
+/// `(text "foo" (effects (justify left)))`.
+#[macro_export(local_inner_macros)]
+macro_rules! tagged {
+	(
+		#[tag($($tag:tt),+)]
+		$(#[$attr:meta])*
+		$vis:vis enum $name:ident {
+			$(
+				$(#[$variant_attr:meta])*
+				$variant:ident($inner:ty) = $value:literal
+			),+
+		}
+	) => {
+		$(#[$attr])*
+		#[derive($crate::private::serde::Serialize)]
+		#[serde(crate = "::serde_kicad_sexpr::private::serde", untagged)]
+		$vis enum $name {
+			$(
+				$(#[$variant_attr])*
+				$variant($inner)
+			),+
+		}
+
+		impl<'de> $crate::private::serde::Deserialize<'de> for $name
+		where
+			$($inner: $crate::private::serde::Deserialize<'de>),*
+		{
+			fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+			where
+				D: $crate::private::serde::Deserializer<'de>
+			{
+				// the path to the tag, the values of the tag and the names of the
+				// s-exprs of the variants
+				static VARIANTS: $crate::private::SyncLazy<
+					::std::vec::Vec<&'static str>
+				> = $crate::private::SyncLazy::new(|| ::std::vec![
+					::std::stringify!($($tag),+),
+					$($value,)+
+					$($crate::private::tagged_name::<$inner>()),+
+				]);
+				let (_, values, _) = $crate::private::split_tagged(&VARIANTS);
+
+				struct Visitor(&'static [&'static str]);
+
+				impl<'de> $crate::private::serde::de::Visitor<'de> for Visitor {
+					type Value = $name;
+
+					fn expecting(
+						&self, f: &mut ::std::fmt::Formatter<'_>
+					) -> ::std::fmt::Result {
+						::std::fmt::Display::fmt(&::std::format_args!(
+							"any s-expr with a tag in {:?}",
+							self.0
+						), f)
+					}
+
+					fn visit_enum<A>(self, data: A) -> ::std::result::Result<$name, A::Error>
+					where
+						A: $crate::private::serde::de::EnumAccess<'de>
+					{
+						let (tag, variant): (::std::borrow::Cow<'de, str>, _) =
+							data.variant()?;
+
+						$(
+							if tag == $value {
+								let inner: $inner =
+									$crate::private::serde::de::VariantAccess::newtype_variant(
+										variant
+									)?;
+								return ::std::result::Result::Ok($name::$variant(inner));
+							}
+						)+
+
+						return ::std::result::Result::Err(
+							<A::Error as $crate::private::serde::de::Error>::unknown_variant(
+								&tag,
+								self.0
+							)
+						);
+					}
+				}
+
+				deserializer.deserialize_enum(
+					$crate::private::TAGGED,
+					&VARIANTS,
+					Visitor(values)
+				)
+			}
+		}
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	mod shapes {
+		use serde::{Deserialize, Serialize};
+
+		#[derive(Debug, Deserialize, PartialEq, Serialize)]
+		#[serde(deny_unknown_fields, rename = "justify")]
+		pub(super) struct Justify(pub(super) String);
+
+		#[derive(Debug, Deserialize, PartialEq, Serialize)]
+		#[serde(deny_unknown_fields, rename = "effects")]
+		pub(super) struct Effects {
+			pub(super) justify: Justify
+		}
+
+		#[derive(Debug, Deserialize, PartialEq, Serialize)]
+		#[serde(deny_unknown_fields, rename = "text")]
+		pub(super) struct Text {
+			pub(super) content: String,
+			pub(super) effects: Effects
+		}
+
+		#[derive(Debug, Deserialize, PartialEq, Serialize)]
+		#[serde(deny_unknown_fields, rename = "text")]
+		pub(super) struct Mirrored {
+			pub(super) content: String,
+			pub(super) effects: Effects
+		}
+
+		#[derive(Debug, Deserialize, PartialEq, Serialize)]
+		#[serde(deny_unknown_fields, rename = "board")]
+		pub(super) struct Board {
+			#[serde(default, rename = "")]
+			pub(super) texts: Vec<super::Aligned>
+		}
+	}
+
+	use serde::Deserialize;
+	use shapes::{Board, Effects, Justify, Mirrored, Text};
+
+	// a local alias must not break the generated code
+	type Result<T> = std::result::Result<T, crate::de::Error>;
+
+	tagged! {
+		#[tag(effects, justify)]
+		#[derive(Debug, PartialEq)]
+		enum Aligned {
+			Left(Text) = "left",
+			Mirrored(Mirrored) = "mirror"
+		}
+	}
+
+	fn effects(justify: &str) -> Effects {
+		Effects {
+			justify: Justify(justify.to_owned())
+		}
+	}
+
+	#[test]
+	fn round_trip_named_tag() {
+		let input = r#"(board (text "A 1" (effects (justify left))) (text "B 2" (effects (justify mirror))))"#;
+		let expected = Board {
+			texts: vec![
+				Aligned::Left(Text {
+					content: "A 1".to_owned(),
+					effects: effects("left")
+				}),
+				Aligned::Mirrored(Mirrored {
+					content: "B 2".to_owned(),
+					effects: effects("mirror")
+				}),
+			]
+		};
+
+		let parsed: Board = crate::from_str(input).expect("Failed to parse input");
+		assert_eq!(parsed, expected);
+		let written = crate::to_string(&parsed).expect("Failed to write input");
+		assert_eq!(written, input);
+	}
+
+	#[test]
+	fn deserialize_lenient() {
+		let input = r#"(board (uuid "a1") (text "A 1" (effects (justify left))))"#;
+		let mut de = crate::de::Deserializer::from_str(input).lenient(true);
+		let parsed = Board::deserialize(&mut de).expect("Failed to parse input");
+		assert_eq!(parsed.texts, [Aligned::Left(Text {
+			content: "A 1".to_owned(),
+			effects: effects("left")
+		})]);
+	}
+
+	#[test]
+	fn deserialize_unknown_tag() {
+		let input = r#"(text "a" (effects (justify right)))"#;
+		let result: Result<Aligned> = crate::from_str(input);
+		let err = result.unwrap_err();
+		assert!(err.to_string().contains("unknown variant `right`"));
+	}
+
+	#[test]
+	fn deserialize_missing_tag() {
+		let input = r#"(text "a" (effects))"#;
+		let err = crate::from_str::<Aligned>(input).unwrap_err();
+		assert_eq!(
+			err.kind,
+			crate::de::ErrorKind::MissingTag("effects, justify")
+		);
+	}
+}
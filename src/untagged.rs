@@ -1,69 +1,117 @@
+/// Define an enum whose variant is selected by the next value, rather than by a
+/// tag.
+///
+/// Variants that wrap a struct are selected by the name of the s-expr. Variants
+/// that wrap a string, an integer or a float are selected by the kind of the
+/// value; an integer falls back to a float variant and any value falls back to a
+/// string variant. Unit variants are written as a symbol, which defaults to the
+/// name of the variant and can be changed after the `=`. The enum can be generic
+/// over `'static` types.
+///
+/// ### Example
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+///
+/// serde_kicad_sexpr::untagged! {
+///     #[derive(Debug, PartialEq)]
+///     enum Size<T> {
+///         Auto = "auto",
+///         Fixed(T),
+///         Named(String)
+///     }
+/// }
+///
+/// #[derive(Deserialize, Serialize)]
+/// #[serde(rename = "font")]
+/// struct Font(Size<u32>);
+///
+/// let font: Font = serde_kicad_sexpr::from_str("(font auto)").unwrap();
+/// assert_eq!(font.0, Size::Auto);
+/// let font: Font = serde_kicad_sexpr::from_str("(font 12)").unwrap();
+/// assert_eq!(font.0, Size::Fixed(12));
+/// let font: Font = serde_kicad_sexpr::from_str("(font large)").unwrap();
+/// assert_eq!(font.0, Size::Named("large".to_owned()));
+/// ```
 #[macro_export(local_inner_macros)]
 macro_rules! untagged {
 	(
 		$(#[$attr:meta])*
-		$vis:vis enum $name:ident {
+		$vis:vis enum $name:ident $(<$($generic:ident),+>)? {
 			$(
 				$(#[$variant_attr:meta])*
-				$variant:ident($inner:ty)
+				$variant:ident $(($inner:ty))? $(= $symbol:literal)?
 			),+
 		}
 	) => {
 		$(#[$attr])*
-		#[derive(::serde::Serialize)]
-		#[serde(untagged)]
-		$vis enum $name {
+		$vis enum $name $(<$($generic),+>)? {
 			$(
 				$(#[$variant_attr])*
-				$variant($inner)
+				$variant $(($inner))?
 			),+
 		}
 
-		impl<'de> ::serde::Deserialize<'de> for $name
+		impl<$($($generic),+)?> ::serde::Serialize for $name<$($($generic),+)?>
 		where
-			$($inner: ::serde::Deserialize<'de>),*
+			$($($inner: ::serde::Serialize,)?)+
 		{
-			fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+			#[allow(irrefutable_let_patterns)]
+			fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
 			where
-				D: ::serde::Deserializer<'de>
+				S: ::serde::Serializer
 			{
-				static VARIANTS: $crate::private::SyncLazy<
-					::std::result::Result<
-						[&'static str; count!($($variant)+)],
-						::std::string::String
-					>
-				> = $crate::private::SyncLazy::new(|| ::std::result::Result::Ok([$({
-					let extraction = <$inner as ::serde::Deserialize>::deserialize(
-						$crate::private::NameExtractor
+				let value = self;
+				$(
+					untagged!(
+						@serialize value, serializer, $name, $variant,
+						[$($symbol)?] $(, $inner)?
 					);
-					let extraction = match extraction {
-						::std::result::Result::Ok(_) => ::std::unreachable!(),
-						::std::result::Result::Err(e) => e
-					};
-					match extraction {
-						$crate::private::Extraction::Ok(name) => name,
-						$crate::private::Extraction::Err(err) => return Err(err)
-					}
-				}),+]));
-				let variants: &'static [&'static str] = VARIANTS
-					.as_ref()
-					.map_err(|err| <D::Error as ::serde::de::Error>::custom(err))?;
+				)+
+				::std::unreachable!()
+			}
+		}
+
+		impl<'de, $($($generic: 'static),+)?> ::serde::Deserialize<'de>
+			for $name<$($($generic),+)?>
+		where
+			$($($inner: ::serde::Deserialize<'de>,)?)+
+		{
+			fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+			where
+				D: ::serde::Deserializer<'de>
+			{
+				let variants = untagged!(
+					@variants [$($($generic),+)?], $name<$($($generic),+)?>,
+					$(untagged!(@name $variant, [$($symbol)?] $(, $inner)?)),+
+				)
+				.map_err(<D::Error as ::serde::de::Error>::custom)?;
 
-				struct Visitor(&'static [&'static str]);
+				struct Visitor<$($($generic),+)?>(
+					&'static [&'static str],
+					::std::marker::PhantomData<fn() -> $name<$($($generic),+)?>>
+				);
 
-				impl<'de> ::serde::de::Visitor<'de> for Visitor {
-					type Value = $name;
+				impl<'de, $($($generic),+)?> ::serde::de::Visitor<'de>
+					for Visitor<$($($generic),+)?>
+				where
+					$($($inner: ::serde::Deserialize<'de>,)?)+
+				{
+					type Value = $name<$($($generic),+)?>;
 
 					fn expecting(
 						&self, f: &mut ::std::fmt::Formatter<'_>
 					) -> ::std::fmt::Result {
 						::std::fmt::Display::fmt(&::std::format_args!(
-							"any s-expr with a name in {:?}",
+							"any value or s-expr matching one of {:?}",
 							self.0
 						), f)
 					}
 
-					fn visit_enum<A>(self, data: A) -> ::std::result::Result<$name, A::Error>
+					fn visit_enum<A>(
+						self,
+						data: A
+					) -> ::std::result::Result<Self::Value, A::Error>
 					where
 						A: ::serde::de::EnumAccess<'de>
 					{
@@ -73,9 +121,9 @@ macro_rules! untagged {
 						let mut i = 0;
 						$(
 							if variant_name == self.0[i] {
-								let inner: $inner =
-									::serde::de::VariantAccess::newtype_variant(variant)?;
-								return ::std::result::Result::Ok($name::$variant(inner));
+								return untagged!(
+									@deserialize variant, $name, $variant $(, $inner)?
+								);
 							}
 							i += 1;
 						)+
@@ -93,23 +141,76 @@ macro_rules! untagged {
 				deserializer.deserialize_enum(
 					::std::stringify!($name),
 					variants,
-					Visitor(variants)
+					Visitor(variants, ::std::marker::PhantomData)
 				)
 			}
 		}
 	};
-}
 
-#[macro_export(local_inner_macros)]
-#[doc(hidden)]
-macro_rules! count {
-	() => {
-		0
+	(@serialize $value:ident, $ser:ident, $name:ident, $variant:ident, [], $inner:ty) => {
+		if let $name::$variant(inner) = $value {
+			return ::serde::Serialize::serialize(inner, $ser);
+		}
 	};
 
-	($x:ident $($xs:ident)*) => {
-		1 + count!($($xs)*)
-	}
+	(@serialize $value:ident, $ser:ident, $name:ident, $variant:ident, [$($symbol:literal)?]) => {
+		if let $name::$variant = $value {
+			// the variant index is not used by this format
+			return ::serde::Serializer::serialize_unit_variant(
+				$ser,
+				::std::stringify!($name),
+				0,
+				untagged!(@symbol $variant, [$($symbol)?])
+			);
+		}
+	};
+
+	// the names of a non-generic enum are computed once
+	(@variants [], $ty:ty, $($name:expr),+) => {{
+		static VARIANTS: $crate::private::SyncLazy<
+			::std::result::Result<
+				::std::vec::Vec<&'static str>,
+				::std::string::String
+			>
+		> = $crate::private::SyncLazy::new(|| [$($name),+].into_iter().collect());
+		VARIANTS.as_deref().map_err(::std::clone::Clone::clone)
+	}};
+
+	// statics are shared by all instances of a generic enum, so the names of every
+	// instance are looked up by its type
+	(@variants [$($generic:ident),+], $ty:ty, $($name:expr),+) => {
+		$crate::private::generic_variants::<$ty, _>(|| {
+			[$($name),+].into_iter().collect()
+		})
+	};
+
+	(@name $variant:ident, [], $inner:ty) => {
+		$crate::private::variant_name::<$inner>()
+	};
+
+	(@name $variant:ident, [$($symbol:literal)?]) => {
+		::std::result::Result::<_, ::std::string::String>::Ok(
+			untagged!(@symbol $variant, [$($symbol)?])
+		)
+	};
+
+	(@symbol $variant:ident, [$symbol:literal]) => {
+		$symbol
+	};
+
+	(@symbol $variant:ident, []) => {
+		::std::stringify!($variant)
+	};
+
+	(@deserialize $access:ident, $name:ident, $variant:ident, $inner:ty) => {{
+		let inner: $inner = ::serde::de::VariantAccess::newtype_variant($access)?;
+		::std::result::Result::Ok($name::$variant(inner))
+	}};
+
+	(@deserialize $access:ident, $name:ident, $variant:ident) => {{
+		::serde::de::VariantAccess::unit_variant($access)?;
+		::std::result::Result::Ok($name::$variant)
+	}};
 }
 
 #[cfg(test)]
@@ -127,6 +228,7 @@ mod tests {
 	}
 
 	use foo_bar::{Bar, Foo};
+	use serde::{Deserialize, Serialize};
 
 	untagged! {
 		#[derive(Debug, PartialEq)]
@@ -155,4 +257,90 @@ mod tests {
 			crate::from_str(input).expect("Failed to parse input");
 		assert_eq!(parsed, expected);
 	}
+
+	untagged! {
+		#[derive(Debug, PartialEq)]
+		enum TextOrNumber {
+			Text(String),
+			Int(i32),
+			Float(f32)
+		}
+	}
+
+	untagged! {
+		#[derive(Debug, PartialEq)]
+		enum Fill<T> {
+			None = "none",
+			Solid,
+			Custom(T)
+		}
+	}
+
+	#[derive(Debug, Deserialize, PartialEq, Serialize)]
+	#[serde(deny_unknown_fields, rename = "values")]
+	struct Values {
+		#[serde(default, rename = "")]
+		values: Vec<TextOrNumber>
+	}
+
+	#[derive(Debug, Deserialize, PartialEq, Serialize)]
+	#[serde(deny_unknown_fields, rename = "fills")]
+	struct Fills {
+		#[serde(default, rename = "")]
+		fills: Vec<Fill<Bar>>
+	}
+
+	#[test]
+	fn round_trip_primitives() {
+		let input = r#"(values "A 1" text 1 -2 1.5)"#;
+		let expected = Values {
+			values: vec![
+				TextOrNumber::Text("A 1".to_owned()),
+				TextOrNumber::Text("text".to_owned()),
+				TextOrNumber::Int(1),
+				TextOrNumber::Int(-2),
+				TextOrNumber::Float(1.5),
+			]
+		};
+
+		let parsed: Values = crate::from_str(input).expect("Failed to parse input");
+		assert_eq!(parsed, expected);
+		let written = crate::to_string(&parsed).expect("Failed to write input");
+		assert_eq!(written, input);
+
+		// timestamps are not floats, and are quoted like all text with digits
+		let parsed: Values = crate::from_str("(values 5E000012)").unwrap();
+		assert_eq!(parsed.values, [TextOrNumber::Text("5E000012".to_owned())]);
+		let written = crate::to_string(&parsed).expect("Failed to write input");
+		assert_eq!(written, r#"(values "5E000012")"#);
+	}
+
+	#[test]
+	fn round_trip_generic_units() {
+		let input = "(fills none Solid (bar))";
+		let expected = Fills {
+			fills: vec![Fill::None, Fill::Solid, Fill::Custom(Bar)]
+		};
+
+		let parsed: Fills = crate::from_str(input).expect("Failed to parse input");
+		assert_eq!(parsed, expected);
+		let written = crate::to_string(&parsed).expect("Failed to write input");
+		assert_eq!(written, input);
+	}
+
+	#[test]
+	fn deserialize_generic_instances() {
+		let parsed: Fill<Foo> = crate::from_str("(foo)").expect("Failed to parse");
+		assert_eq!(parsed, Fill::Custom(Foo));
+		let parsed: Fill<Bar> = crate::from_str("(bar)").expect("Failed to parse");
+		assert_eq!(parsed, Fill::Custom(Bar));
+		assert!(crate::from_str::<Fill<Bar>>("(foo)").is_err());
+	}
+
+	#[test]
+	fn deserialize_unknown_symbol() {
+		let input = "(fills solid)";
+		let err = crate::from_str::<Fills>(input).unwrap_err();
+		assert!(err.to_string().contains("invalid value: solid"));
+	}
 }
@@ -0,0 +1,283 @@
+use serde::{
+	de::{self, Deserialize, Deserializer, SeqAccess, Visitor},
+	ser::{Serialize, SerializeTupleStruct, Serializer}
+};
+use std::fmt::{self, Formatter};
+
+/// The magic tuple struct name used to serialize an untyped s-expr. Its first field is
+/// the name of the s-expr, followed by its values.
+pub(crate) const LIST: &str = "$serde_kicad_sexpr::private::List";
+
+/// The magic newtype struct name used to serialize a symbol.
+pub(crate) const SYMBOL: &str = "$serde_kicad_sexpr::private::Symbol";
+
+/// The magic newtype struct name used to serialize a quoted string.
+pub(crate) const STRING: &str = "$serde_kicad_sexpr::private::String";
+
+/// The magic newtype struct name used to serialize a float that is written with all
+/// of its digits.
+pub(crate) const EXACT_FLOAT: &str = "$serde_kicad_sexpr::private::ExactFloat";
+
+/// The magic newtype struct name used to deserialize a [`Value`], so that it can be
+/// told apart from other self-describing types.
+pub(crate) const VALUE: &str = "$serde_kicad_sexpr::private::Value";
+
+/// An untyped representation of any s-expression.
+///
+/// This is useful to inspect documents whose structure is not known in advance, or
+/// to keep parts of a document that are not modelled by your own types.
+///
+/// ### Example
+///
+/// ```rust
+/// use serde_kicad_sexpr::Value;
+///
+/// let value: Value = serde_kicad_sexpr::from_str(r#"(layer "F.Cu" signal)"#).unwrap();
+/// assert_eq!(value.head(), Some("layer"));
+/// assert_eq!(value.items()[0], Value::String("F.Cu".to_owned()));
+/// assert_eq!(value.items()[1], Value::Symbol("signal".to_owned()));
+/// assert_eq!(serde_kicad_sexpr::to_string(&value).unwrap(), r#"(layer "F.Cu" signal)"#);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+	/// An s-expr like `(name values...)`.
+	List(String, Vec<Value>),
+
+	/// An unquoted string, like `F.Cu` or `smd`.
+	Symbol(String),
+
+	/// A quoted string, like `"C_0402"`.
+	String(String),
+
+	/// An integer. Integers that do not fit into an `i64` are read as symbols, so
+	/// that they are written back unchanged.
+	Int(i64),
+
+	/// A finite floating point number. Tokens like `inf`, `nan` or `5E241234`
+	/// are read as symbols instead. Unlike typed floats, it is written with all of
+	/// its digits and a decimal point, so that it reads back as the same float.
+	Float(f64)
+}
+
+impl Value {
+	/// Return the name of this s-expr, or `None` if this is not a list.
+	pub fn head(&self) -> Option<&str> {
+		match self {
+			Self::List(head, _) => Some(head),
+			_ => None
+		}
+	}
+
+	/// Return the values of this s-expr. This is empty if this is not a list.
+	pub fn items(&self) -> &[Value] {
+		match self {
+			Self::List(_, items) => items,
+			_ => &[]
+		}
+	}
+
+	/// Return the first child s-expr with the given name.
+	pub fn get(&self, head: &str) -> Option<&Value> {
+		self.items().iter().find(|item| item.head() == Some(head))
+	}
+}
+
+impl Serialize for Value {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer
+	{
+		match self {
+			Self::List(head, items) => {
+				let mut list = serializer.serialize_tuple_struct(LIST, items.len() + 1)?;
+				list.serialize_field(head.as_str())?;
+				for item in items {
+					list.serialize_field(item)?;
+				}
+				list.end()
+			},
+			Self::Symbol(symbol) => serializer.serialize_newtype_struct(SYMBOL, symbol),
+			Self::String(string) => serializer.serialize_newtype_struct(STRING, string),
+			Self::Int(int) => serializer.serialize_i64(*int),
+			Self::Float(float) => serializer.serialize_newtype_struct(EXACT_FLOAT, float)
+		}
+	}
+}
+
+/// Unwrap the magic newtype struct of a [`Value`]. Deserializers that don't know
+/// about it can also pass the value itself.
+struct NewtypeVisitor;
+
+impl<'de> Visitor<'de> for NewtypeVisitor {
+	type Value = Value;
+
+	fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		ValueVisitor.expecting(f)
+	}
+
+	fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+	where
+		D: Deserializer<'de>
+	{
+		deserializer.deserialize_any(ValueVisitor)
+	}
+
+	fn visit_i64<E>(self, v: i64) -> Result<Value, E>
+	where
+		E: de::Error
+	{
+		ValueVisitor.visit_i64(v)
+	}
+
+	fn visit_u64<E>(self, v: u64) -> Result<Value, E>
+	where
+		E: de::Error
+	{
+		ValueVisitor.visit_u64(v)
+	}
+
+	fn visit_f64<E>(self, v: f64) -> Result<Value, E>
+	where
+		E: de::Error
+	{
+		ValueVisitor.visit_f64(v)
+	}
+
+	fn visit_str<E>(self, v: &str) -> Result<Value, E>
+	where
+		E: de::Error
+	{
+		ValueVisitor.visit_str(v)
+	}
+
+	fn visit_string<E>(self, v: String) -> Result<Value, E>
+	where
+		E: de::Error
+	{
+		ValueVisitor.visit_string(v)
+	}
+
+	fn visit_seq<A>(self, seq: A) -> Result<Value, A::Error>
+	where
+		A: SeqAccess<'de>
+	{
+		ValueVisitor.visit_seq(seq)
+	}
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+	type Value = Value;
+
+	fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str("any s-expression value")
+	}
+
+	fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+		Ok(Value::Int(v))
+	}
+
+	fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+		Ok(match i64::try_from(v) {
+			Ok(v) => Value::Int(v),
+			// keep all digits instead of rounding to a float
+			Err(_) => Value::Symbol(v.to_string())
+		})
+	}
+
+	fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+		Ok(Value::Float(v))
+	}
+
+	fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+		Ok(Value::String(v.to_owned()))
+	}
+
+	fn visit_string<E>(self, v: String) -> Result<Value, E> {
+		Ok(Value::String(v))
+	}
+
+	fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+	where
+		D: Deserializer<'de>
+	{
+		String::deserialize(deserializer).map(Value::Symbol)
+	}
+
+	fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+	where
+		A: SeqAccess<'de>
+	{
+		let head: String = seq
+			.next_element()?
+			.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+		let mut items = Vec::new();
+		while let Some(item) = seq.next_element()? {
+			items.push(item);
+		}
+		Ok(Value::List(head, items))
+	}
+}
+
+impl<'de> Deserialize<'de> for Value {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>
+	{
+		deserializer.deserialize_newtype_struct(VALUE, NewtypeVisitor)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Value;
+
+	const INPUT: &str = r#"(footprint "C_0402" (layer F.Cu) (at 1.5 -2) (pad "1" smd rect))"#;
+
+	#[test]
+	fn deserialize() {
+		let value: Value = crate::from_str(INPUT).unwrap();
+		assert_eq!(
+			value,
+			Value::List("footprint".to_owned(), vec![
+				Value::String("C_0402".to_owned()),
+				Value::List("layer".to_owned(), vec![Value::Symbol(
+					"F.Cu".to_owned()
+				)]),
+				Value::List("at".to_owned(), vec![
+					Value::Float(1.5),
+					Value::Int(-2)
+				]),
+				Value::List("pad".to_owned(), vec![
+					Value::String("1".to_owned()),
+					Value::Symbol("smd".to_owned()),
+					Value::Symbol("rect".to_owned())
+				])
+			])
+		);
+		assert_eq!(value.get("at").unwrap().items()[1], Value::Int(-2));
+	}
+
+	#[test]
+	fn round_trip() {
+		let value: Value = crate::from_str(INPUT).unwrap();
+		assert_eq!(crate::to_string(&value).unwrap(), INPUT);
+	}
+
+	#[test]
+	fn round_trip_names_with_digits() {
+		let input = "(pcbplotparams (psa4output false) (hpglpennumber 1) \
+			(dxfpolygonmode true) (layerselection 0x00010fc_ffffffff))";
+		let value: Value = crate::from_str(input).unwrap();
+		let psa4output = value.get("psa4output").unwrap();
+		assert_eq!(psa4output.items(), [Value::Symbol("false".to_owned())]);
+		assert_eq!(crate::to_string(&value).unwrap(), input);
+	}
+
+	#[test]
+	fn serialize_root_atom() {
+		crate::to_string(&Value::Symbol("smd".to_owned())).unwrap_err();
+		crate::to_string(&Value::Int(1)).unwrap_err();
+	}
+}
@@ -2,7 +2,12 @@ use indoc::indoc;
 use paste::paste;
 use pretty_assertions::assert_eq;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::fmt::Debug;
+use serde_kicad_sexpr::{ser::PrettyConfig, Extra, Value};
+use std::{
+	borrow::Cow,
+	collections::BTreeMap,
+	fmt::{self, Debug}
+};
 
 fn assert_eq_parsed<T>(input: &str, expected: &T)
 where
@@ -20,6 +25,10 @@ where
 	let written =
 		serde_kicad_sexpr::to_string(input).expect("Failed to write input");
 	assert_eq!(written.as_str(), expected);
+
+	let mut buf = Vec::new();
+	serde_kicad_sexpr::to_writer(&mut buf, input).expect("Failed to write input");
+	assert_eq!(buf, written.as_bytes());
 }
 
 fn assert_eq_pretty<T>(input: &T, expected: &str)
@@ -29,6 +38,11 @@ where
 	let written =
 		serde_kicad_sexpr::to_string_pretty(input).expect("Failed to write input");
 	assert_eq!(written.as_str(), expected.trim_end_matches('\n'));
+
+	let mut buf = Vec::new();
+	serde_kicad_sexpr::to_writer_pretty(&mut buf, input)
+		.expect("Failed to write input");
+	assert_eq!(buf, written.as_bytes());
 }
 
 macro_rules! test_case {
@@ -109,6 +123,67 @@ fn test_deserialize_locked_trailing_tokens() {
 	assert_eq!(err.kind, serde_kicad_sexpr::de::ErrorKind::TrailingTokens);
 }
 
+#[test]
+fn test_deserialize_locked_from_reader() {
+	let input: &[u8] = b"(locked)";
+	let parsed: Locked =
+		serde_kicad_sexpr::from_reader(input).expect("Failed to parse input");
+	assert_eq!(parsed, Locked);
+}
+
+#[test]
+fn test_deserialize_locked_from_failing_reader() {
+	use std::{error::Error, io};
+
+	struct FailingReader;
+
+	impl io::Read for FailingReader {
+		fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+			Err(io::Error::other("disk on fire"))
+		}
+	}
+
+	let err =
+		serde_kicad_sexpr::from_reader::<_, Locked>(FailingReader).unwrap_err();
+	assert_eq!(err.to_string(), "I/O error: disk on fire");
+	let source = err.source().expect("Error should have a source");
+	let source = source.downcast_ref::<io::Error>().unwrap();
+	assert_eq!(source.to_string(), "disk on fire");
+}
+
+#[test]
+fn test_serialize_locked_to_failing_writer() {
+	use std::{error::Error, io};
+
+	struct FailingWriter;
+
+	impl io::Write for FailingWriter {
+		fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+			Err(io::Error::other("disk full"))
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	let err = serde_kicad_sexpr::to_writer(FailingWriter, &Locked).unwrap_err();
+	assert_eq!(err.to_string(), "I/O error: disk full");
+	assert_eq!(err.source().unwrap().to_string(), "disk full");
+
+	let written = serde_kicad_sexpr::to_vec_kicad(&Locked).unwrap();
+	assert_eq!(written, b"(locked)\n");
+}
+
+#[test]
+fn test_deserialize_invalid_utf8() {
+	let input = b"(locked\n  \xff)";
+	let err = serde_kicad_sexpr::from_slice::<Locked>(input).unwrap_err();
+	assert_eq!(err.kind, serde_kicad_sexpr::de::ErrorKind::InvalidUtf8);
+	let pos = err.position().expect("Error should have a position");
+	assert_eq!((pos.offset, pos.line, pos.column), (10, 2, 3));
+}
+
 // ##################################################################################
 
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
@@ -135,6 +210,59 @@ test_case! {
 	)
 }
 
+// KiCad escapes backslashes, quotes and line breaks, but keeps tabs and unicode
+test_case! {
+	name: descr_multiline,
+	input: "(descr \"Line 1\\nLine 2\\r\\n\tTabbed \\\"quote\\\" C:\\\\path 10Ω\")",
+	value: Description(
+		"Line 1\nLine 2\r\n\tTabbed \"quote\" C:\\path 10Ω".to_owned()
+	)
+}
+
+#[test]
+fn test_deserialize_descr_escapes() {
+	let input = r#"(descr "\t\a\b\f\v \x41\x4 \101\60 \316\251\xce\xa9 \q\x")"#;
+	assert_eq_parsed(
+		input,
+		&Description("\t\x07\x08\x0c\x0b A\x04 A0 ΩΩ \\q\\x".to_owned())
+	);
+}
+
+#[test]
+fn test_deserialize_descr_invalid_utf8() {
+	let input = r#"(descr "\xff")"#;
+	let err = serde_kicad_sexpr::from_str::<Description>(input).unwrap_err();
+	assert_eq!(err.to_string(), "Invalid UTF-8 sequence at 1:8 in descr > 0");
+}
+
+// ##################################################################################
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields, rename = "net")]
+struct BorrowedNet<'a> {
+	code: u32,
+	name: &'a str,
+	#[serde(borrow)]
+	class: Cow<'a, str>
+}
+
+#[test]
+fn test_deserialize_borrowed_strings() {
+	let input = r#"(net 1 "GND" Default)"#;
+	let net: BorrowedNet<'_> = serde_kicad_sexpr::from_str(input).unwrap();
+	assert_eq!(net.name, "GND");
+	assert!(matches!(net.class, Cow::Borrowed("Default")));
+
+	let input = r#"(net 2 VCC "Power \"5V\"")"#;
+	let net: BorrowedNet<'_> = serde_kicad_sexpr::from_str(input).unwrap();
+	assert_eq!(net.name, "VCC");
+	assert!(matches!(net.class, Cow::Owned(class) if class == r#"Power "5V""#));
+
+	// escaped strings cannot be borrowed
+	let input = r#"(net 3 "A\\B" Default)"#;
+	serde_kicad_sexpr::from_str::<BorrowedNet<'_>>(input).unwrap_err();
+}
+
 // ##################################################################################
 
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
@@ -166,6 +294,179 @@ test_case! {
 	}
 }
 
+#[test]
+fn test_deserialize_position_error_location() {
+	let input = "(at 1.23\n  abc)";
+	let err = serde_kicad_sexpr::from_str::<Position>(input).unwrap_err();
+	let span = err.span().expect("Error should have a span");
+	assert_eq!((span.start.line, span.start.column), (2, 3));
+	assert_eq!(span.start.offset, 11);
+	assert_eq!((span.end.line, span.end.column), (2, 6));
+	assert_eq!(err.to_string(), "invalid float literal at 2:3 in at > y");
+}
+
+#[test]
+fn test_deserialize_position_number_grammar() {
+	assert_eq_parsed("(at 1e-3 .5 +90)", &Position {
+		x: 0.001,
+		y: 0.5,
+		rot: Some(90)
+	});
+	assert_eq_parsed("(at 2.5E+1 -1. -0)", &Position {
+		x: 25.0,
+		y: -1.0,
+		rot: Some(0)
+	});
+}
+
+#[test]
+fn test_deserialize_value_numbers() {
+	let input = "(at 1.0e-3 .5 +5 -2 - 1e nan5 inf NaN 1e-3)";
+	assert_eq_parsed(input, &Value::List("at".to_owned(), vec![
+		Value::Float(0.001),
+		Value::Float(0.5),
+		Value::Int(5),
+		Value::Int(-2),
+		Value::Symbol("-".to_owned()),
+		Value::Symbol("1e".to_owned()),
+		Value::Symbol("nan5".to_owned()),
+		Value::Symbol("inf".to_owned()),
+		Value::Symbol("NaN".to_owned()),
+		Value::Float(0.001)
+	]));
+}
+
+#[test]
+fn test_round_trip_value_large_int() {
+	// integers that do not fit into an i64 keep all of their digits
+	let input = "(uuid 18446744073709551615 -9223372036854775808)";
+	let value = Value::List("uuid".to_owned(), vec![
+		Value::Symbol("18446744073709551615".to_owned()),
+		Value::Int(i64::MIN)
+	]);
+	assert_eq_parsed(input, &value);
+	assert_eq_ugly(&value, input);
+}
+
+#[test]
+fn test_round_trip_value_overflowing_int() {
+	// integers that do not fit into 64 bits are kept as symbols
+	let input = "(uuid -99999999999999999999 99999999999999999999999)";
+	let value = Value::List("uuid".to_owned(), vec![
+		Value::Symbol("-99999999999999999999".to_owned()),
+		Value::Symbol("99999999999999999999999".to_owned())
+	]);
+	assert_eq_parsed(input, &value);
+	assert_eq_ugly(&value, input);
+}
+
+#[test]
+fn test_round_trip_value_exact_float() {
+	// floats of values are not rounded, and stay floats when they are read back
+	let input = "(at 1.234567891 1.0 -0.5)";
+	let value = Value::List("at".to_owned(), vec![
+		Value::Float(1.234567891),
+		Value::Float(1.0),
+		Value::Float(-0.5)
+	]);
+	assert_eq_parsed(input, &value);
+	assert_eq_ugly(&value, input);
+}
+
+#[test]
+fn test_round_trip_value_timestamp() {
+	// these timestamps look like floats in scientific notation
+	for timestamp in ["5E241234", "5E000012", "1E000000"] {
+		let input = format!("(tedit {timestamp})");
+		let value = Value::List("tedit".to_owned(), vec![Value::Symbol(
+			timestamp.to_owned()
+		)]);
+		assert_eq_parsed(&input, &value);
+		assert_eq_ugly(&value, &input);
+	}
+}
+
+#[test]
+fn test_round_trip_position_non_finite() {
+	let position = Position {
+		x: f32::INFINITY,
+		y: f32::NEG_INFINITY,
+		rot: None
+	};
+	assert_eq_ugly(&position, "(at inf -inf)");
+	assert_eq_parsed("(at inf -inf)", &position);
+
+	let parsed: Position = serde_kicad_sexpr::from_str("(at NaN 0)").unwrap();
+	assert!(parsed.x.is_nan());
+
+	// only typed fields read these names as floats
+	let parsed: Value = serde_kicad_sexpr::from_str("(at inf -inf)").unwrap();
+	assert_eq!(parsed.items(), [
+		Value::Symbol("inf".to_owned()),
+		Value::Symbol("-inf".to_owned())
+	]);
+}
+
+#[test]
+fn test_strict_floats() {
+	use serde_kicad_sexpr::{de::Deserializer, ser::Serializer};
+
+	for input in ["(at 0 nan)", "(at 0 -inf)", "(at 0 1e39)"] {
+		let mut de = Deserializer::from_str(input).strict_floats(true);
+		let err = Position::deserialize(&mut de).unwrap_err();
+		assert_eq!(err.to_string(), "Non-finite float at 1:7 in at > y");
+	}
+
+	let position = Position {
+		x: 0.0,
+		y: f32::NAN,
+		rot: None
+	};
+	let mut ser = Serializer::new(String::new()).strict_floats(true);
+	let err = position.serialize(&mut ser).unwrap_err();
+	assert_eq!(err, serde_kicad_sexpr::ser::Error::NonFiniteFloat);
+}
+
+#[test]
+fn test_serialize_position_float_format() {
+	use serde_kicad_sexpr::ser::{FloatFormat, Serializer};
+
+	let position = Position {
+		x: 254.1,
+		y: -0.000_000_1,
+		rot: None
+	};
+	let written = serde_kicad_sexpr::to_string(&position).unwrap();
+	assert_eq!(written, "(at 254.1 0)");
+	let written = serde_kicad_sexpr::to_string_kicad(&position).unwrap();
+	assert_eq!(written, "(at 254.1 0)\n");
+	let mut ser =
+		Serializer::new(String::new()).float_format(FloatFormat::shortest());
+	position.serialize(&mut ser).unwrap();
+	assert_eq!(ser.into_inner(), "(at 254.1 -0.0000001)");
+
+	// more decimals than an f32 can hold do not show its binary noise
+	let float_format = FloatFormat::kicad().max_decimals(Some(10));
+	let mut ser = Serializer::new(String::new()).float_format(float_format);
+	position.serialize(&mut ser).unwrap();
+	assert_eq!(ser.into_inner(), "(at 254.1 -0.0000001)");
+
+	let position = Position {
+		x: 1.000_000_1,
+		y: 2.5,
+		rot: Some(90)
+	};
+	let written = serde_kicad_sexpr::to_string_kicad(&position).unwrap();
+	assert_eq!(written, "(at 1 2.5 90)\n");
+
+	let float_format = FloatFormat::kicad()
+		.max_decimals(Some(2))
+		.trim_zeros(false);
+	let mut ser = Serializer::new(String::new()).float_format(float_format);
+	position.serialize(&mut ser).unwrap();
+	assert_eq!(ser.into_inner(), "(at 1.00 2.50 90)");
+}
+
 // ##################################################################################
 
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
@@ -254,6 +555,119 @@ test_case! {
 
 // ##################################################################################
 
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "xy")]
+struct Point(f32, f32);
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "polygon")]
+struct Polygon {
+	locked: Option<()>,
+
+	#[serde(default, rename = "")]
+	points: Vec<Point>
+}
+
+test_case! {
+	name: polygon_unlocked,
+	input: "(polygon (xy 1 2) (xy 3 4))",
+	pretty: indoc!(r#"
+		(polygon
+		  (xy 1 2)
+		  (xy 3 4))
+	"#),
+	value: Polygon {
+		locked: None,
+		points: vec![Point(1.0, 2.0), Point(3.0, 4.0)]
+	}
+}
+
+test_case! {
+	name: polygon_locked,
+	input: "(polygon (locked) (xy 1 2))",
+	pretty: indoc!(r#"
+		(polygon
+		  (locked)
+		  (xy 1 2))
+	"#),
+	value: Polygon {
+		locked: Some(()),
+		points: vec![Point(1.0, 2.0)]
+	}
+}
+
+// ##################################################################################
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "pad")]
+struct PadNumber(
+	String,
+	#[serde(with = "serde_kicad_sexpr::Option")] Option<u32>,
+	#[serde(with = "serde_kicad_sexpr::Option")] Option<Size>
+);
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(rename = "pad")]
+struct PlainPadNumber(String, Option<u32>, Option<Size>);
+
+test_case! {
+	name: pad_number_unnumbered,
+	input: r#"(pad "1" (size 1 1))"#,
+	pretty: indoc!(r#"
+		(pad "1"
+		  (size 1 1))
+	"#),
+	value: PadNumber("1".into(), None, Some(Size(1.0, 1.0)))
+}
+
+#[test]
+fn test_check_layout_unnamed_option() {
+	serde_kicad_sexpr::check_layout::<PadNumber>().unwrap();
+	let err = serde_kicad_sexpr::check_layout::<PlainPadNumber>().unwrap_err();
+	assert_eq!(err.to_string(), indoc!(r#"
+		pad > 1: Cannot tell if the option is present, use serde_kicad_sexpr::Option
+		pad > 2: Cannot tell if the option is present, use serde_kicad_sexpr::Option"#));
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "net")]
+struct OptionalNet {
+	#[serde(with = "serde_kicad_sexpr::Option")]
+	code: Option<u32>,
+	#[serde(with = "serde_kicad_sexpr::Option")]
+	name: Option<String>
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(rename = "net")]
+struct PlainOptionalNet {
+	code: Option<u32>,
+	name: Option<String>
+}
+
+test_case! {
+	name: optional_net_unnumbered,
+	input: "(net GND)",
+	value: OptionalNet {
+		code: None,
+		name: Some("GND".into())
+	}
+}
+
+#[test]
+fn test_check_layout_option_before_token() {
+	serde_kicad_sexpr::check_layout::<OptionalNet>().unwrap();
+	let err = serde_kicad_sexpr::check_layout::<PlainOptionalNet>().unwrap_err();
+	assert_eq!(
+		err.to_string(),
+		"net > code: Cannot tell if the option is present, use serde_kicad_sexpr::Option"
+	);
+}
+
+// ##################################################################################
+
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 enum PadType {
 	#[serde(rename = "thru-hole")]
@@ -376,6 +790,118 @@ test_case! {
 
 // ##################################################################################
 
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "at")]
+struct PlainPosition {
+	x: f32,
+	y: f32,
+	rot: Option<i16>
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "drill")]
+struct PlainDrill {
+	oval: bool,
+	drill1: f32,
+	drill2: Option<f32>
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "pad")]
+struct PlainPad {
+	index: String,
+	ty: PadType,
+	shape: PadShape,
+	at: PlainPosition,
+	size: Size,
+	drill: Option<PlainDrill>,
+	layers: Vec<String>
+}
+
+test_case! {
+	name: plain_pad_without_drill,
+	input: r#"(pad "1" smd rect (at 1.5 -2 90) (size 1.27 1.27) (layers "F.Cu"))"#,
+	pretty: indoc!(r#"
+		(pad "1" smd rect
+		  (at 1.5 -2 90)
+		  (size 1.27 1.27)
+		  (layers "F.Cu"))
+	"#),
+	value: PlainPad {
+		index: "1".into(),
+		ty: PadType::Smd,
+		shape: PadShape::Rect,
+		at: PlainPosition {
+			x: 1.5,
+			y: -2.0,
+			rot: Some(90)
+		},
+		size: Size(1.27, 1.27),
+		drill: None,
+		layers: vec!["F.Cu".to_owned()]
+	}
+}
+
+test_case! {
+	name: plain_pad_with_drill,
+	input: r#"(pad "1" thru-hole rect (at 0 0) (size 1.27 1.27) (drill 0.635) (layers "F.Cu"))"#,
+	pretty: indoc!(r#"
+		(pad "1" thru-hole rect
+		  (at 0 0)
+		  (size 1.27 1.27)
+		  (drill 0.635)
+		  (layers "F.Cu"))
+	"#),
+	value: PlainPad {
+		index: "1".into(),
+		ty: PadType::ThroughHole,
+		shape: PadShape::Rect,
+		at: PlainPosition {
+			x: 0.0,
+			y: 0.0,
+			rot: None
+		},
+		size: Size(1.27, 1.27),
+		drill: Some(PlainDrill {
+			oval: false,
+			drill1: 0.635,
+			drill2: None
+		}),
+		layers: vec!["F.Cu".to_owned()]
+	}
+}
+
+test_case! {
+	name: plain_pad_with_oval_drill,
+	input: r#"(pad "1" thru-hole rect (at 0 0) (size 1.27 1.27) (drill oval 0.635 0.847) (layers "F.Cu"))"#,
+	pretty: indoc!(r#"
+		(pad "1" thru-hole rect
+		  (at 0 0)
+		  (size 1.27 1.27)
+		  (drill oval 0.635 0.847)
+		  (layers "F.Cu"))
+	"#),
+	value: PlainPad {
+		index: "1".into(),
+		ty: PadType::ThroughHole,
+		shape: PadShape::Rect,
+		at: PlainPosition {
+			x: 0.0,
+			y: 0.0,
+			rot: None
+		},
+		size: Size(1.27, 1.27),
+		drill: Some(PlainDrill {
+			oval: true,
+			drill1: 0.635,
+			drill2: Some(0.847)
+		}),
+		layers: vec!["F.Cu".to_owned()]
+	}
+}
+
+// ##################################################################################
+
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 #[serde(deny_unknown_fields, rename = "footprint")]
 struct Footprint {
@@ -468,3 +994,996 @@ test_case! {
 		]
 	}
 }
+
+#[test]
+fn test_deserialize_footprint_error_path() {
+	let input = r#"(footprint "Capacitor_SMD:C_0402" (pad "1" smd rect (at 0 0) (size 1.27 1.27) (layers "F.Cu")) (pad "2" smd rect (at 2.54 x) (size 1.27 1.27) (layers "F.Cu")))"#;
+	let err = serde_kicad_sexpr::from_str::<Footprint>(input).unwrap_err();
+	let path = err
+		.path()
+		.iter()
+		.map(|segment| segment.to_string())
+		.collect::<Vec<_>>();
+	assert_eq!(path, ["footprint", "pad[1]", "at", "y"]);
+	assert_eq!(
+		err.to_string(),
+		"invalid float literal at 1:123 in footprint > pad[1] > at > y"
+	);
+}
+
+// ##################################################################################
+
+fn assert_eq_kicad<T>(input: &T, expected: &str)
+where
+	T: ?Sized + Serialize
+{
+	let written =
+		serde_kicad_sexpr::to_string_kicad(input).expect("Failed to write input");
+	assert_eq!(written.as_str(), expected);
+
+	let mut buf = Vec::new();
+	serde_kicad_sexpr::to_writer_kicad(&mut buf, input)
+		.expect("Failed to write input");
+	assert_eq!(buf, written.as_bytes());
+}
+
+#[test]
+fn test_serialize_kicad_footprint() {
+	let value: Footprint =
+		serde_kicad_sexpr::from_str(TEST_CASE_INPUT_FOOTPRINT_WITH_TWO_PADS)
+			.unwrap();
+	assert_eq_kicad(&value, indoc!(r#"
+		(footprint "Capacitor_SMD:C_0402"
+			(pad "1" smd rect
+				(at 0 0)
+				(size 1.27 1.27)
+				(layers "F.Cu")
+			)
+			(pad "2" smd rect
+				(at 2.54 0)
+				(size 1.27 1.27)
+				(layers "F.Cu")
+			)
+		)
+	"#));
+}
+
+#[test]
+fn test_serialize_kicad_schematic() {
+	let input = r#"(kicad_sch (version 20231120) (generator "eeschema") (generator_version "8.0") (uuid "d6e3c3c4-2b8f-4a0e-9c43-0c6c1a7a1f35") (paper "A4") (lib_symbols) (junction (at 110.49 62.23) (diameter 0) (color 0 0 0 0) (uuid "0e2f6a8c-4f7b-4f2e-8d0e-2b8a8d1c9e11")) (wire (pts (xy 100.33 62.23) (xy 110.49 62.23)) (stroke (width 0) (type default)) (uuid "1a2b3c4d-5e6f-4a1b-8c2d-3e4f5a6b7c8d")) (polyline (pts (xy 1.27 2.54) (xy 3.81 5.08) (xy 6.35 7.62) (xy 8.89 10.16) (xy 11.43 12.7) (xy 13.97 15.24) (xy 16.51 17.78) (xy 19.05 20.32) (xy 21.59 22.86))) (text "Some \"quoted\" (text)" (exclude_from_sim no) (at 50.8 40.64 0) (effects (font (size 1.27 1.27)) (justify left bottom))) (image (at 63.5 63.5) (data "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA" "60e6kgAAAABJRU5ErkJggg==" "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA" "BBBB" "CCCC")))"#;
+	let value: serde_kicad_sexpr::Value =
+		serde_kicad_sexpr::from_str(input).unwrap();
+	assert_eq_kicad(&value, indoc!(r#"
+		(kicad_sch
+			(version 20231120)
+			(generator "eeschema")
+			(generator_version "8.0")
+			(uuid "d6e3c3c4-2b8f-4a0e-9c43-0c6c1a7a1f35")
+			(paper "A4")
+			(lib_symbols)
+			(junction
+				(at 110.49 62.23)
+				(diameter 0)
+				(color 0 0 0 0)
+				(uuid "0e2f6a8c-4f7b-4f2e-8d0e-2b8a8d1c9e11")
+			)
+			(wire
+				(pts
+					(xy 100.33 62.23) (xy 110.49 62.23)
+				)
+				(stroke
+					(width 0)
+					(type default)
+				)
+				(uuid "1a2b3c4d-5e6f-4a1b-8c2d-3e4f5a6b7c8d")
+			)
+			(polyline
+				(pts
+					(xy 1.27 2.54) (xy 3.81 5.08) (xy 6.35 7.62) (xy 8.89 10.16) (xy 11.43 12.7) (xy 13.97 15.24) (xy 16.51 17.78)
+					(xy 19.05 20.32) (xy 21.59 22.86)
+				)
+			)
+			(text "Some \"quoted\" (text)"
+				(exclude_from_sim no)
+				(at 50.8 40.64 0)
+				(effects
+					(font
+						(size 1.27 1.27)
+					)
+					(justify left bottom)
+				)
+			)
+			(image
+				(at 63.5 63.5)
+				(data "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA"
+					"60e6kgAAAABJRU5ErkJggg==" "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+					"BBBB" "CCCC"
+				)
+			)
+		)
+	"#));
+}
+
+const TEST_CASE_INPUT_GR_POLY: &str = r#"(gr_poly (pts (xy 1.27 2.54) (xy 3.81 5.08) (xy 6.35 7.62)) (stroke (width 0.1) (type solid)) (fill none) (layer "F.SilkS") (effects (font (size 1.27 1.27) (thickness 0.15)) (justify left)))"#;
+
+#[test]
+fn test_serialize_config_kicad_short_forms() {
+	let value: serde_kicad_sexpr::Value =
+		serde_kicad_sexpr::from_str(TEST_CASE_INPUT_GR_POLY).unwrap();
+	let config = PrettyConfig::kicad().always_inline([
+		"font", "stroke", "fill", "teardrop", "offset", "rotate", "scale"
+	]);
+	let written =
+		serde_kicad_sexpr::to_string_with_config(&value, &config).unwrap();
+	assert_eq!(written, indoc!(r#"
+		(gr_poly
+			(pts
+				(xy 1.27 2.54) (xy 3.81 5.08) (xy 6.35 7.62)
+			)
+			(stroke (width 0.1) (type solid))
+			(fill none)
+			(layer "F.SilkS")
+			(effects
+				(font (size 1.27 1.27) (thickness 0.15))
+				(justify left)
+			)
+		)
+	"#));
+}
+
+#[test]
+fn test_serialize_config_custom() {
+	let value: serde_kicad_sexpr::Value =
+		serde_kicad_sexpr::from_str(TEST_CASE_INPUT_GR_POLY).unwrap();
+	let config = PrettyConfig::new()
+		.indent("    ")
+		.max_width(Some(20))
+		.always_inline(["effects"])
+		.always_break(["font"]);
+	let written =
+		serde_kicad_sexpr::to_string_with_config(&value, &config).unwrap();
+	assert_eq!(written, indoc!(r#"
+		(gr_poly
+		    (pts
+		        (xy 1.27 2.54)
+		        (xy 3.81 5.08)
+		        (xy 6.35 7.62))
+		    (stroke
+		        (width 0.1)
+		        (type solid))
+		    (fill none)
+		    (layer "F.SilkS")
+		    (effects
+		        (font (size 1.27 1.27) (thickness 0.15)) (justify left)))
+	"#).trim_end_matches('\n'));
+}
+
+// ##################################################################################
+
+fn parse_unordered<'de, T>(
+	input: &'de str
+) -> Result<T, serde_kicad_sexpr::de::Error>
+where
+	T: Deserialize<'de>
+{
+	let mut de = serde_kicad_sexpr::de::Deserializer::from_str(input).unordered(true);
+	T::deserialize(&mut de)
+}
+
+#[test]
+fn test_deserialize_unordered_pad() {
+	let input = r#"(pad "1" (layers "F.Cu") thru-hole (drill 0.635 oval 0.847) (size 1.27 1.27) rect (at 0 0))"#;
+	let value: Pad = parse_unordered(input).unwrap();
+	assert_eq!(value, Pad {
+		index: "1".into(),
+		ty: PadType::ThroughHole,
+		shape: PadShape::Rect,
+		at: Position {
+			x: 0.0,
+			y: 0.0,
+			rot: None
+		},
+		size: Size(1.27, 1.27),
+		drill: Some(Drill {
+			oval: true,
+			drill1: 0.635,
+			drill2: Some(0.847)
+		}),
+		layers: vec!["F.Cu".to_owned()]
+	});
+}
+
+#[test]
+fn test_deserialize_unordered_font() {
+	let value: Font = parse_unordered("(font bold (size 1.27 1.27))").unwrap();
+	assert_eq!(value, Font {
+		size: Size(1.27, 1.27),
+		thickness: None,
+		bold: true
+	});
+}
+
+#[test]
+fn test_deserialize_unordered_collection() {
+	let input = r#"(footprint (pad "1" smd rect (at 0 0) (size 1.27 1.27) (layers "F.Cu")) "Capacitor_SMD:C_0402")"#;
+	let value: Footprint = parse_unordered(input).unwrap();
+	assert_eq!(value.library_link, "Capacitor_SMD:C_0402");
+	assert_eq!(value.pads.len(), 1);
+
+	let value: Polygon =
+		parse_unordered("(polygon (xy 1 2) (locked) (xy 3 4))").unwrap();
+	assert_eq!(value, Polygon {
+		locked: Some(()),
+		points: vec![Point(1.0, 2.0), Point(3.0, 4.0)]
+	});
+}
+
+#[test]
+fn test_deserialize_unordered_unknown_child() {
+	let err = parse_unordered::<Line>("(line (end 1 2) (width 1) (start 3 4))")
+		.unwrap_err();
+	assert_eq!(err.to_string(), "Expected end of expression at 1:17 in line");
+}
+
+#[test]
+fn test_deserialize_unordered_structs() {
+	let input = "(line (end 1 2) (start 3 4) (locked))";
+	let mut de = serde_kicad_sexpr::de::Deserializer::from_str(input)
+		.unordered_structs(["line"]);
+	let value = Line::deserialize(&mut de).unwrap();
+	assert_eq!(value, Line {
+		start: (3.0, 4.0),
+		end: (1.0, 2.0),
+		locked: Some(())
+	});
+
+	let mut de = serde_kicad_sexpr::de::Deserializer::from_str(input)
+		.unordered_structs(["pad"]);
+	Line::deserialize(&mut de).unwrap_err();
+}
+
+// ##################################################################################
+
+fn parse_lenient<'de, T>(input: &'de str) -> Result<T, serde_kicad_sexpr::de::Error>
+where
+	T: Deserialize<'de>
+{
+	let mut de = serde_kicad_sexpr::de::Deserializer::from_str(input).lenient(true);
+	T::deserialize(&mut de)
+}
+
+#[test]
+fn test_deserialize_lenient_pad() {
+	let input = r#"(pad "1" smd rect (at 0 0) (size 1.27 1.27) (uuid "a1b2") (layers "F.Cu") (remove_unused_layers (keep no)))"#;
+	let value: Pad = parse_lenient(input).unwrap();
+	assert_eq!(value, Pad {
+		index: "1".into(),
+		ty: PadType::Smd,
+		shape: PadShape::Rect,
+		at: Position {
+			x: 0.0,
+			y: 0.0,
+			rot: None
+		},
+		size: Size(1.27, 1.27),
+		drill: None,
+		layers: vec!["F.Cu".to_owned()]
+	});
+
+	serde_kicad_sexpr::from_str::<Pad>(input).unwrap_err();
+}
+
+#[test]
+fn test_deserialize_lenient_font() {
+	let input = r#"(font (size 1.27 1.27) (face "KiCad Font") bold)"#;
+	let value: Font = parse_lenient(input).unwrap();
+	assert_eq!(value, Font {
+		size: Size(1.27, 1.27),
+		thickness: None,
+		bold: true
+	});
+
+	parse_lenient::<Font>("(font (size 1.27 1.27) 42 bold)").unwrap_err();
+}
+
+#[test]
+fn test_deserialize_lenient_collection() {
+	let input = r#"(footprint "C_0402" (version 20240108) (pad "1" smd rect (at 0 0) (size 1.27 1.27) (layers "F.Cu")) (embedded_fonts no) (pad "2" smd rect (at 1 0) (size 1.27 1.27) (layers "F.Cu")) (embedded_files (file "a")))"#;
+	let value: Footprint = parse_lenient(input).unwrap();
+	assert_eq!(value.library_link, "C_0402");
+	assert_eq!(value.pads.len(), 2);
+	assert_eq!(value.pads[1].index, "2");
+
+	let value: Polygon =
+		parse_lenient("(polygon (xy 1 2) (fill none) (xy 3 4) (uuid x))").unwrap();
+	assert_eq!(value, Polygon {
+		locked: None,
+		points: vec![Point(1.0, 2.0), Point(3.0, 4.0)]
+	});
+}
+
+#[test]
+fn test_deserialize_lenient_unordered() {
+	let input = "(line (end 1 2) (width 1) (start 3 4))";
+	let mut de = serde_kicad_sexpr::de::Deserializer::from_str(input)
+		.unordered(true)
+		.lenient(true);
+	let value = Line::deserialize(&mut de).unwrap();
+	assert_eq!(value, Line {
+		start: (3.0, 4.0),
+		end: (1.0, 2.0),
+		locked: None
+	});
+}
+
+#[test]
+fn test_deserialize_ignored_any() {
+	let input = r#"(footprint "C_0402" (at 1 (2)) "\")")"#;
+	serde_kicad_sexpr::from_str::<serde::de::IgnoredAny>(input).unwrap();
+}
+
+// ##################################################################################
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "footprint")]
+struct FootprintWithExtra {
+	#[serde(default, rename = "$extra")]
+	extra: Extra,
+
+	library_link: String,
+
+	#[serde(default, rename = "")]
+	pads: Vec<Pad>
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "font")]
+struct FontWithExtra {
+	#[serde(default, rename = "$extra")]
+	extra: Extra,
+
+	size: Size,
+	bold: bool
+}
+
+#[test]
+fn test_extra_collection() {
+	let input = r#"(footprint "C_0402" (version 20240108) (pad "1" smd rect (at 0 0) (size 1.27 1.27) (layers "F.Cu")) (embedded_fonts no) (pad "2" smd rect (at 1 0) (size 1.27 1.27) (layers "F.Cu")) (embedded_files (file "a")))"#;
+	let value: FootprintWithExtra = serde_kicad_sexpr::from_str(input).unwrap();
+	assert_eq!(value.pads.len(), 2);
+	let positions = value
+		.extra
+		.iter()
+		.map(|(index, value)| (index, value.head().unwrap()))
+		.collect::<Vec<_>>();
+	assert_eq!(positions, [
+		(Some(1), "version"),
+		(Some(3), "embedded_fonts"),
+		(Some(5), "embedded_files")
+	]);
+	assert_eq_ugly(&value, input);
+}
+
+#[test]
+fn test_extra_struct() {
+	let input = r#"(font (face "KiCad Font") (size 1.27 1.27) (italic yes) bold (color 0 0 0 1))"#;
+	let value: FontWithExtra = serde_kicad_sexpr::from_str(input).unwrap();
+	assert_eq!(value.size, Size(1.27, 1.27));
+	assert!(value.bold);
+	assert_eq!(value.extra.len(), 3);
+	assert_eq!(
+		value.extra.get("italic").unwrap().items(),
+		[Value::Symbol("yes".to_owned())]
+	);
+	assert_eq_ugly(&value, input);
+	assert_eq_kicad(&value, indoc!(r#"
+		(font
+			(face "KiCad Font")
+			(size 1.27 1.27)
+			(italic yes) bold
+			(color 0 0 0 1)
+		)
+	"#));
+
+	let mut de =
+		serde_kicad_sexpr::de::Deserializer::from_str(input).unordered(true);
+	assert_eq!(FontWithExtra::deserialize(&mut de).unwrap(), value);
+}
+
+#[test]
+fn test_extra_push() {
+	let mut value = FontWithExtra {
+		extra: Extra::new(),
+		size: Size(1.0, 1.0),
+		bold: false
+	};
+	value.extra.push(Value::List("face".to_owned(), vec![Value::String(
+		"KiCad Font".to_owned()
+	)]));
+	value.extra.push(Value::List("italic".to_owned(), vec![Value::Symbol(
+		"yes".to_owned()
+	)]));
+	assert_eq!(value.extra.iter().next().unwrap().0, None);
+	assert_eq_ugly(&value, r#"(font (size 1 1) (face "KiCad Font") (italic yes))"#);
+
+	let mut value: FontWithExtra =
+		serde_kicad_sexpr::from_str("(font (italic yes) (size 1 1))").unwrap();
+	value.extra.push(Value::List("color".to_owned(), vec![Value::Int(0)]));
+	assert_eq_ugly(&value, "(font (italic yes) (size 1 1) (color 0))");
+}
+
+#[test]
+fn test_extra_misplaced() {
+	#[derive(Debug, Deserialize)]
+	#[serde(rename = "font")]
+	struct Font {
+		_extra: Extra
+	}
+
+	let err = serde_kicad_sexpr::from_str::<Font>("(font (face x))").unwrap_err();
+	assert_eq!(err.kind, serde_kicad_sexpr::de::ErrorKind::MisplacedExtra);
+}
+
+// ##################################################################################
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "property")]
+struct Property {
+	value: String,
+	at: Position
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "symbol")]
+struct Symbol {
+	lib_id: String,
+	#[serde(rename = "property")]
+	properties: BTreeMap<String, Property>,
+	locked: bool
+}
+
+test_case! {
+	name: symbol,
+	input: r#"(symbol "Device:R" (property Reference "R1" (at 0 0)) (property Value "10k" (at 0 2)) locked)"#,
+	pretty: indoc!(r#"
+		(symbol "Device:R"
+		  (property Reference "R1"
+		    (at 0 0))
+		  (property Value "10k"
+		    (at 0 2)) locked)
+	"#),
+	value: Symbol {
+		lib_id: "Device:R".to_owned(),
+		properties: [
+			("Reference".to_owned(), Property {
+				value: "R1".to_owned(),
+				at: Position {
+					x: 0.0,
+					y: 0.0,
+					rot: None
+				}
+			}),
+			("Value".to_owned(), Property {
+				value: "10k".to_owned(),
+				at: Position {
+					x: 0.0,
+					y: 2.0,
+					rot: None
+				}
+			}),
+		]
+		.into_iter()
+		.collect(),
+		locked: true
+	}
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "nets")]
+struct Nets {
+	#[serde(rename = "net")]
+	nets: BTreeMap<u32, String>
+}
+
+test_case! {
+	name: nets,
+	input: r#"(nets (net 0 "") (net 1 GND) (net 2 "+3V3"))"#,
+	pretty: indoc!(r#"
+		(nets
+		  (net 0 "")
+		  (net 1 GND)
+		  (net 2 "+3V3"))
+	"#),
+	value: Nets {
+		nets: [
+			(0, "".to_owned()),
+			(1, "GND".to_owned()),
+			(2, "+3V3".to_owned())
+		]
+		.into_iter()
+		.collect()
+	}
+}
+
+test_case! {
+	name: nets_empty,
+	input: "(nets)",
+	value: Nets {
+		nets: BTreeMap::new()
+	}
+}
+
+#[test]
+fn test_deserialize_nets_error_path() {
+	let input = r#"(nets (net 0 "") (net x GND))"#;
+	let err = serde_kicad_sexpr::from_str::<Nets>(input).unwrap_err();
+	assert_eq!(err.path().len(), 2);
+	assert_eq!(err.path()[1].to_string(), "net[1]");
+}
+
+#[test]
+fn test_deserialize_symbol_unordered() {
+	let input = r#"(symbol (property Value "10k" (at 0 2)) locked "Device:R" (property Reference "R1" (at 0 0)))"#;
+	let mut de =
+		serde_kicad_sexpr::de::Deserializer::from_str(input).unordered(true);
+	let symbol = Symbol::deserialize(&mut de).unwrap();
+	assert_eq!(symbol.lib_id, "Device:R");
+	assert_eq!(symbol.properties["Reference"].value, "R1");
+	assert_eq!(symbol.properties["Value"].at.y, 2.0);
+	assert!(symbol.locked);
+}
+
+// ##################################################################################
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "center")]
+struct Center(f32, f32);
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "radius")]
+struct Radius(f32);
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum Shape {
+	Circle { center: Center, radius: Radius },
+	Line(Point, Point),
+	Polygon(Polygon)
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "drawing")]
+struct Drawing {
+	#[serde(default, rename = "")]
+	shapes: Vec<Shape>
+}
+
+test_case! {
+	name: drawing,
+	input: "(drawing (circle (center 0 0) (radius 1.5)) (line (xy 0 0) (xy 1 1)) (polygon (xy 1 2)))",
+	pretty: indoc!(r#"
+		(drawing
+		  (circle
+		    (center 0 0)
+		    (radius 1.5))
+		  (line
+		    (xy 0 0)
+		    (xy 1 1))
+		  (polygon
+		    (xy 1 2)))
+	"#),
+	value: Drawing {
+		shapes: vec![
+			Shape::Circle {
+				center: Center(0.0, 0.0),
+				radius: Radius(1.5)
+			},
+			Shape::Line(Point(0.0, 0.0), Point(1.0, 1.0)),
+			Shape::Polygon(Polygon {
+				locked: None,
+				points: vec![Point(1.0, 2.0)]
+			}),
+		]
+	}
+}
+
+test_case! {
+	name: shape_line,
+	input: "(line (xy 0 0) (xy 1 1))",
+	pretty: indoc!(r#"
+		(line
+		  (xy 0 0)
+		  (xy 1 1))
+	"#),
+	value: Shape::Line(Point(0.0, 0.0), Point(1.0, 1.0))
+}
+
+test_case! {
+	name: shape_circle,
+	input: "(circle (center 0 0) (radius 1.5))",
+	pretty: indoc!(r#"
+		(circle
+		  (center 0 0)
+		  (radius 1.5))
+	"#),
+	value: Shape::Circle {
+		center: Center(0.0, 0.0),
+		radius: Radius(1.5)
+	}
+}
+
+#[test]
+fn test_deserialize_shape_error_path() {
+	let input = "(drawing (line (xy 0 0) (xy 1 x)))";
+	let err = serde_kicad_sexpr::from_str::<Drawing>(input).unwrap_err();
+	assert_eq!(
+		err.to_string(),
+		"invalid float literal at 1:31 in drawing > line[0] > 1 > xy > 1"
+	);
+}
+
+#[test]
+fn test_deserialize_unit_variant_in_sexpr() {
+	#[derive(Debug, Deserialize)]
+	#[serde(rename_all = "snake_case")]
+	enum Marker {
+		Hidden,
+		Radius(Radius)
+	}
+
+	let marker = serde_kicad_sexpr::from_str::<Marker>("(radius 1)").unwrap();
+	assert!(matches!(marker, Marker::Radius(Radius(radius)) if radius == 1.0));
+	let err = serde_kicad_sexpr::from_str::<Marker>("(hidden)").unwrap_err();
+	assert_eq!(err.kind, serde_kicad_sexpr::de::ErrorKind::UnitVariantInSExpr);
+}
+
+// ##################################################################################
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "circle")]
+struct Circle {
+	center: Center,
+	radius: Radius
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "sheet")]
+struct Sheet {
+	title: String,
+	#[serde(default)]
+	locked: bool,
+	#[serde(default, rename = "line*")]
+	lines: Vec<Line>,
+	#[serde(default, rename = "circle*")]
+	circles: Vec<Circle>
+}
+
+fn line(start: (f32, f32), end: (f32, f32)) -> Line {
+	Line {
+		start,
+		end,
+		locked: None
+	}
+}
+
+test_case! {
+	name: sheet,
+	input: "(sheet Main locked (line (start 0 0) (end 1 1)) (line (start 1 1) (end 2 0)) (circle (center 0 0) (radius 1.5)))",
+	pretty: indoc!(r#"
+		(sheet Main locked
+		  (line
+		    (start 0 0)
+		    (end 1 1))
+		  (line
+		    (start 1 1)
+		    (end 2 0))
+		  (circle
+		    (center 0 0)
+		    (radius 1.5)))
+	"#),
+	value: Sheet {
+		title: "Main".to_owned(),
+		locked: true,
+		lines: vec![line((0.0, 0.0), (1.0, 1.0)), line((1.0, 1.0), (2.0, 0.0))],
+		circles: vec![Circle {
+			center: Center(0.0, 0.0),
+			radius: Radius(1.5)
+		}]
+	}
+}
+
+#[test]
+fn test_deserialize_sheet_interleaved() {
+	let input = "(sheet (line (start 0 0) (end 1 1)) \
+		(circle (center 0 0) (radius 1.5)) Main (line (start 1 1) (end 2 0)))";
+	assert_eq_parsed(input, &Sheet {
+		title: "Main".to_owned(),
+		locked: false,
+		lines: vec![line((0.0, 0.0), (1.0, 1.0)), line((1.0, 1.0), (2.0, 0.0))],
+		circles: vec![Circle {
+			center: Center(0.0, 0.0),
+			radius: Radius(1.5)
+		}]
+	});
+}
+
+#[test]
+fn test_deserialize_sheet_unknown_child() {
+	let input = "(sheet Main (line (start 0 0) (end 1 1)) (arc (xy 0 0)))";
+	let err = serde_kicad_sexpr::from_str::<Sheet>(input).unwrap_err();
+	assert_eq!(err.to_string(), "Expected end of expression at 1:42 in sheet");
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "sheet")]
+struct SheetWithExtra {
+	#[serde(default, rename = "$extra")]
+	extra: Extra,
+	title: String,
+	#[serde(default, rename = "line*")]
+	lines: Vec<Line>,
+	#[serde(default, rename = "circle*")]
+	circles: Vec<Circle>
+}
+
+#[test]
+fn test_extra_interleaved() {
+	let input = "(sheet Main (line (start 0 0) (end 1 1)) (arc (mid 0 0)) \
+		(circle (center 0 0) (radius 1.5)) (line (start 1 1) (end 2 0)))";
+	let mut value: SheetWithExtra = serde_kicad_sexpr::from_str(input).unwrap();
+	assert_eq!(value.lines.len(), 2);
+	assert_eq!(value.circles.len(), 1);
+	assert_eq_ugly(&value, input);
+	assert_eq_kicad(&value, indoc!(r#"
+		(sheet Main
+			(line
+				(start 0 0)
+				(end 1 1)
+			)
+			(arc
+				(mid 0 0)
+			)
+			(circle
+				(center 0 0)
+				(radius 1.5)
+			)
+			(line
+				(start 1 1)
+				(end 2 0)
+			)
+		)
+	"#));
+
+	value.lines.pop();
+	value.circles.push(Circle {
+		center: Center(1.0, 1.0),
+		radius: Radius(1.0)
+	});
+	assert_eq_ugly(
+		&value,
+		"(sheet Main (line (start 0 0) (end 1 1)) (arc (mid 0 0)) \
+		 (circle (center 0 0) (radius 1.5)) (circle (center 1 1) (radius 1)))"
+	);
+}
+
+// ##################################################################################
+
+serde_kicad_sexpr::untagged! {
+	#[derive(Debug, PartialEq)]
+	enum Graphic {
+		Line(Line),
+		Circle(Circle)
+	}
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "layer")]
+struct Layer {
+	#[serde(default, rename = "$extra")]
+	extra: Extra,
+	name: String,
+	#[serde(default, rename = "")]
+	graphics: Vec<Graphic>
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename = "group")]
+struct Group {
+	name: String,
+	members: Vec<Graphic>
+}
+
+fn circle(radius: f32) -> Circle {
+	Circle {
+		center: Center(0.0, 0.0),
+		radius: Radius(radius)
+	}
+}
+
+test_case! {
+	name: layer,
+	input: "(layer Top (line (start 0 0) (end 1 1)) (circle (center 0 0) (radius 1)) (line (start 1 1) (end 2 0)))",
+	pretty: indoc!(r#"
+		(layer Top
+		  (line
+		    (start 0 0)
+		    (end 1 1))
+		  (circle
+		    (center 0 0)
+		    (radius 1))
+		  (line
+		    (start 1 1)
+		    (end 2 0)))
+	"#),
+	value: Layer {
+		extra: Extra::new(),
+		name: "Top".to_owned(),
+		graphics: vec![
+			Graphic::Line(line((0.0, 0.0), (1.0, 1.0))),
+			Graphic::Circle(circle(1.0)),
+			Graphic::Line(line((1.0, 1.0), (2.0, 0.0))),
+		]
+	}
+}
+
+test_case! {
+	name: group,
+	input: "(group G (members (circle (center 0 0) (radius 1)) (line (start 0 0) (end 1 1)) (circle (center 0 0) (radius 2))))",
+	pretty: indoc!(r#"
+		(group G
+		  (members
+		    (circle
+		      (center 0 0)
+		      (radius 1))
+		    (line
+		      (start 0 0)
+		      (end 1 1))
+		    (circle
+		      (center 0 0)
+		      (radius 2))))
+	"#),
+	value: Group {
+		name: "G".to_owned(),
+		members: vec![
+			Graphic::Circle(circle(1.0)),
+			Graphic::Line(line((0.0, 0.0), (1.0, 1.0))),
+			Graphic::Circle(circle(2.0)),
+		]
+	}
+}
+
+#[test]
+fn test_round_trip_layer_unordered_with_extra() {
+	let input = "(layer Top (circle (center 0 0) (radius 1)) (arc (mid 0 1)) \
+		(line (start 0 0) (end 1 1)) (arc (mid 1 0)))";
+	let mut de =
+		serde_kicad_sexpr::de::Deserializer::from_str(input).unordered(true);
+	let layer = Layer::deserialize(&mut de).unwrap();
+	assert_eq!(layer.graphics, [
+		Graphic::Circle(circle(1.0)),
+		Graphic::Line(line((0.0, 0.0), (1.0, 1.0)))
+	]);
+	assert_eq!(layer.extra.len(), 2);
+	assert_eq!(serde_kicad_sexpr::to_string(&layer).unwrap(), input);
+}
+
+// ##################################################################################
+
+#[test]
+fn test_check_layout_supported() {
+	serde_kicad_sexpr::check_layout::<Footprint>().unwrap();
+	serde_kicad_sexpr::check_layout::<FootprintWithExtra>().unwrap();
+	serde_kicad_sexpr::check_layout::<Symbol>().unwrap();
+	serde_kicad_sexpr::check_layout::<Nets>().unwrap();
+	serde_kicad_sexpr::check_layout::<Drawing>().unwrap();
+	serde_kicad_sexpr::check_layout::<Sheet>().unwrap();
+	serde_kicad_sexpr::check_layout::<Layer>().unwrap();
+	serde_kicad_sexpr::check_layout::<Group>().unwrap();
+	serde_kicad_sexpr::check_layout::<Value>().unwrap();
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(rename = "pin")]
+struct BadPin(String, bool, Vec<String>);
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BadShape {
+	Arc(Point, bool),
+	Polygon(Polygon),
+	Rect(BTreeMap<String, String>)
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Untagged {
+	Text(String)
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(rename = "bad")]
+struct Bad {
+	pin: BadPin,
+	#[serde(rename = "")]
+	shapes: Vec<BadShape>,
+	#[serde(rename = "$extra")]
+	extra: Extra,
+	untagged: Untagged,
+	children: Option<Box<Bad>>
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(rename = "net")]
+struct NetAddr(u32, std::net::Ipv4Addr);
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(rename = "host")]
+struct BadHost(bool, std::net::Ipv4Addr);
+
+/// A self-describing type that claims to expect the same as a [`Value`].
+struct LookalikeValue;
+
+impl<'de> Deserialize<'de> for LookalikeValue {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>
+	{
+		struct LookalikeVisitor;
+
+		impl<'de> serde::de::Visitor<'de> for LookalikeVisitor {
+			type Value = LookalikeValue;
+
+			fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				f.write_str("any s-expression value")
+			}
+
+			fn visit_str<E>(self, _v: &str) -> Result<LookalikeValue, E> {
+				Ok(LookalikeValue)
+			}
+		}
+
+		deserializer.deserialize_any(LookalikeVisitor)
+	}
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(rename = "note")]
+struct Note(Value, LookalikeValue);
+
+#[test]
+fn test_check_layout_value() {
+	let err = serde_kicad_sexpr::check_layout::<Note>().unwrap_err();
+	assert_eq!(
+		err.to_string(),
+		"note > 1: Self-describing types are not supported, use the untagged! macro"
+	);
+}
+
+#[test]
+fn test_check_layout_rejected_placeholder() {
+	serde_kicad_sexpr::check_layout::<NetAddr>().unwrap();
+	let err = serde_kicad_sexpr::check_layout::<BadHost>().unwrap_err();
+	assert_eq!(err.to_string(), "host > 0: Unnamed boolean");
+}
+
+#[test]
+fn test_check_layout_issues() {
+	let err = serde_kicad_sexpr::check_layout::<Bad>().unwrap_err();
+	assert_eq!(err.to_string(), indoc!(r#"
+		bad: Field $extra after the field with an empty name is never read
+		bad: Field untagged after the field with an empty name is never read
+		bad: Field children after the field with an empty name is never read
+		bad: The $extra field has to be the first field
+		bad > pin > 1: Unnamed boolean
+		bad > pin > 2: Unnamed sequence
+		bad > 1 > 0 > arc > 1: Unnamed boolean
+		bad > untagged: Self-describing types are not supported, use the untagged! macro
+		bad > 1 > 0 > rect: maps are only supported in named fields"#));
+}